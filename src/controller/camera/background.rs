@@ -0,0 +1,147 @@
+use opencv::{
+    core::{self, Mat, Ptr},
+    imgproc,
+    prelude::*,
+    video::{self, BackgroundSubtractorKNN, BackgroundSubtractorMOG2},
+};
+use anyhow::Result;
+use serde::Deserialize;
+
+// MOG2 and KNN mark shadows as 127 in the foreground mask; only values above
+// this count as foreground
+const SHADOW_CUTOFF: f64 = 200.0;
+
+/// Kind of background model used for motion detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundModelKind {
+    #[default]
+    RunningAverage, // Exponential moving average of the frames
+    Mog2,           // OpenCV Gaussian mixture model
+    Knn,            // OpenCV K-nearest-neighbours model
+}
+
+/// Settings of the background model
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackgroundConfig {
+    pub model: BackgroundModelKind,
+    pub learning_rate: f64,   // Weight of each new frame in the model; negative lets MOG2/KNN derive it from `history`
+    pub history: i32,         // Frames MOG2/KNN remember
+    pub var_threshold: f64,   // MOG2: squared distance to the model above which a pixel is foreground
+    pub dist2_threshold: f64, // KNN: squared distance to the nearest samples above which a pixel is foreground
+    pub detect_shadows: bool, // MOG2/KNN: recognise shadows so they do not count as motion
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            model: BackgroundModelKind::RunningAverage,
+            learning_rate: 0.01,
+            history: 500,
+            var_threshold: 16.0,
+            dist2_threshold: 400.0,
+            detect_shadows: true,
+        }
+    }
+}
+
+/// Background model that separates moving foreground from the static scene
+///
+/// Every frame is blended into the model with the learning rate, so slow
+/// changes such as daylight are absorbed while anything moving faster stands
+/// out in the foreground mask.
+pub enum BackgroundModel {
+    RunningAverage {
+        average: Option<Mat>, // Floating-point average of the frames, None before the first frame
+        learning_rate: f64,
+        threshold: f64,       // Pixel difference from the average that counts as foreground
+    },
+    Mog2 {
+        subtractor: Ptr<BackgroundSubtractorMOG2>,
+        learning_rate: f64,
+    },
+    Knn {
+        subtractor: Ptr<BackgroundSubtractorKNN>,
+        learning_rate: f64,
+    },
+}
+
+impl BackgroundModel {
+    /// Creates an empty background model
+    ///
+    /// # Arguments
+    /// * `config` - Model kind and its parameters
+    /// * `threshold` - Pixel difference that counts as foreground for the running average
+    pub fn new(config: &BackgroundConfig, threshold: f64) -> Result<Self> {
+        Ok(match config.model {
+            BackgroundModelKind::RunningAverage => BackgroundModel::RunningAverage {
+                average: None,
+                learning_rate: config.learning_rate.clamp(0.0, 1.0),
+                threshold,
+            },
+            BackgroundModelKind::Mog2 => BackgroundModel::Mog2 {
+                subtractor: video::create_background_subtractor_mog2(
+                    config.history,
+                    config.var_threshold,
+                    config.detect_shadows,
+                )?,
+                learning_rate: config.learning_rate,
+            },
+            BackgroundModelKind::Knn => BackgroundModel::Knn {
+                subtractor: video::create_background_subtractor_knn(
+                    config.history,
+                    config.dist2_threshold,
+                    config.detect_shadows,
+                )?,
+                learning_rate: config.learning_rate,
+            },
+        })
+    }
+
+    /// Updates the model with a frame and returns its foreground
+    ///
+    /// # Arguments
+    /// * `gray` - Blurred greyscale frame
+    ///
+    /// # Returns
+    /// * `Result<Option<Mat>>` - Binary foreground mask (255 = foreground), or
+    ///   None while the model has no background yet
+    pub fn apply(&mut self, gray: &Mat) -> Result<Option<Mat>> {
+        let mut mask = Mat::default();
+        match self {
+            BackgroundModel::RunningAverage { average, learning_rate, threshold } => {
+                let Some(average) = average.as_mut() else {
+                    let mut initial = Mat::default();
+                    gray.convert_to_def(&mut initial, core::CV_32F)?;
+                    *average = Some(initial);
+                    return Ok(None);
+                };
+
+                let mut background = Mat::default();
+                average.convert_to_def(&mut background, core::CV_8U)?;
+                let mut diff = Mat::default();
+                core::absdiff(&background, gray, &mut diff)?;
+                imgproc::threshold(&diff, &mut mask, *threshold, 255.0, imgproc::THRESH_BINARY)?;
+
+                imgproc::accumulate_weighted(gray, average, *learning_rate, &Mat::default())?;
+                Ok(Some(mask))
+            }
+            BackgroundModel::Mog2 { subtractor, learning_rate } => {
+                subtractor.apply(gray, &mut mask, *learning_rate)?;
+                Ok(Some(without_shadows(&mask)?))
+            }
+            BackgroundModel::Knn { subtractor, learning_rate } => {
+                subtractor.apply(gray, &mut mask, *learning_rate)?;
+                Ok(Some(without_shadows(&mask)?))
+            }
+        }
+    }
+}
+
+/// Drops the shadow pixels from a MOG2/KNN foreground mask
+fn without_shadows(mask: &Mat) -> Result<Mat> {
+    let mut foreground = Mat::default();
+    imgproc::threshold(mask, &mut foreground, SHADOW_CUTOFF, 255.0, imgproc::THRESH_BINARY)?;
+    Ok(foreground)
+}
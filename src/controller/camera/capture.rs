@@ -1,26 +1,93 @@
 use opencv::{
     core,
-    highgui,
     imgproc,
-    videoio,
     prelude::*,
 };
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::time::Duration;
+use crate::controller::camera::background::{BackgroundConfig, BackgroundModel};
+use crate::controller::camera::catalogue::{Catalogue, EventRecord, FileKind};
+use crate::controller::camera::classifier::ClassifierConfig;
+use crate::controller::camera::config::{CalibrationConfig, LocalizationConfig};
+use crate::controller::camera::event::{BufferedFrame, EventConfig, FrameBuffer};
+use crate::controller::camera::localizer::LedLocalizer;
 use crate::controller::camera::logging;
+use crate::controller::camera::manifest::Manifest;
+use crate::controller::camera::profile::CalibrationProfile;
+use crate::controller::camera::recording::ContinuousRecorder;
+use crate::controller::camera::retention;
+use crate::controller::camera::source::{CameraSource, FrameSource};
 use crate::controller::camera::storage;
+use crate::controller::camera::policy::VerificationState;
+use crate::controller::camera::scene::SceneHealthMonitor;
+use crate::controller::camera::tamper::{TamperDetector, TamperEvent};
+use crate::controller::camera::verdict::Verdict;
+use crate::controller::camera::verifier::{self, Calibration, ExpectedCode, LedRois, LedVerifier};
+use crate::controller::camera::zones::{MotionConfig, MotionResult, MotionZones, WHOLE_FRAME_ZONE};
+use crate::controller::security::protocol::SYMBOL_DURATION;
+
+// How long a reloaded calibration profile is checked against live frames
+pub const PROFILE_CHECK_DURATION: Duration = Duration::from_secs(2);
+
+/// Outcome of running one frame through the pipeline
+#[derive(Debug, Clone)]
+pub struct FrameReport {
+    pub motion_detected: bool,    // Motion was found in this frame
+    pub verdict: Verdict,         // LED pattern verification verdict for this frame
+    pub state: VerificationState, // Verification state over the recent LED frames
+    pub in_event: bool,           // A motion event is currently being recorded
+    pub tamper: Vec<TamperEvent>, // Tampering with the feed or the camera detected in this frame
+    pub motion: MotionResult,     // Motion regions found in this frame
+}
+
+/// Progress of a calibration, passed with every sampled frame
+#[derive(Debug, Clone)]
+pub struct CalibrationProgress {
+    pub sample: usize,          // Samples taken in the current attempt
+    pub attempt: usize,         // Current attempt, from 1
+    pub max_attempts: usize,
+    pub brightnesses: Vec<f64>, // Measured brightness of each LED in the frame
+}
 
 /// Motion detection and video capture system
 pub struct MotionDetector {
-    cap: videoio::VideoCapture,    // Video capture device
-    background: Option<core::Mat>, // Background model for motion detection
-    threshold: f64,                // Threshold for motion detection
-    min_contour_area: f64,         // Minimum contour area to consider as motion
-    storage: storage::VideoStorage,// Video storage handler
-    led_pattern: Option<Vec<bool>> // Expected LED pattern (for tamper detection)
+    source: Box<dyn FrameSource>,     // Frame source (camera, file, images, synthetic)
+    background: BackgroundModel,      // Background model for motion detection
+    zones: MotionZones,               // Include and exclude zones the motion is looked for in
+    threshold: f64,                   // Threshold for motion detection
+    min_contour_area: f64,            // Minimum contour area to consider as motion
+    storage: storage::VideoStorage,   // Video storage handler
+    continuous: Option<ContinuousRecorder>, // 24/7 recording in fixed-length segments
+    catalogue: Option<Catalogue>,     // Sidecars and index of the saved footage
+    manifest: Option<Manifest>,       // Hash chain over the saved footage
+    verifier: Option<LedVerifier>,    // LED pattern verifier (for tamper detection)
+    tamper: Option<TamperDetector>,   // Frozen, looped and stalled feed detection
+    scene: Option<SceneHealthMonitor>,     // Covered, blurred and moved camera detection
+    calibration_config: CalibrationConfig, // Attempt limit, drift bound and profile location
+    classifier_config: ClassifierConfig,   // How LED brightness is measured and classified
+    recalibration: Option<Calibration>,    // Background recalibration in progress
+    recalibration_attempts: usize,         // Failed background recalibrations in a row
+    last_recalibration_sample: Option<Duration>,
+    event_config: EventConfig,        // Pre-roll, post-roll, minimum duration and merge gap
+    pre_roll: FrameBuffer,            // Recent frames outside any event
+    recorder: Option<storage::EventRecorder>, // Clip of the motion event in progress
+    quiet_frames: Vec<BufferedFrame>, // Frames since the last motion, written once known to be in the post-roll
+    in_event: bool,                   // Whether a motion event is in progress
+    motion_started: Duration,         // Timestamp of the first motion frame of the current event
+    last_motion: Duration,            // Timestamp of the latest motion frame of the current event
+    event_verdict: Verdict,           // Least trustworthy verdict seen during the current event
+    event_started_at: DateTime<Local>, // Wall-clock time of the first frame of the current clip
+    last_written: Duration,           // Timestamp of the latest frame written to the current clip
+    event_zone: Option<String>,       // Zone that started the current event
+    event_boxes: Vec<core::Rect>,     // Motion regions of the frame with the most motion in the current event
+    event_peak_area: f64,             // Motion area of that frame
+    event_tamper: Vec<String>,        // Tampering detected during the current event
+    event_suspect: bool,              // Verification was Suspect or Tampered during the current event
 }
 
 impl MotionDetector {
-    /// Creates a new MotionDetector instance
+    /// Creates a new MotionDetector instance reading from a local camera
     /// 
     /// # Arguments
     /// * `camera_index` - Index of the camera device
@@ -37,154 +104,658 @@ impl MotionDetector {
         storage_dir: &str,
     ) -> Result<Self> {
         // Initialize video capture device
-        let cap = videoio::VideoCapture::new(camera_index, videoio::CAP_ANY)
-            .context("Failed to open camera")?;
-        
-        if !cap.is_opened()? {
-            return Err(anyhow::anyhow!("Could not open camera at index {}", camera_index));
-        }
+        let source = CameraSource::new(camera_index)?;
+        Self::from_source(Box::new(source), threshold, min_contour_area, storage_dir)
+    }
 
+    /// Creates a new MotionDetector instance reading from any frame source
+    /// 
+    /// # Arguments
+    /// * `source` - Where frames come from
+    /// * `threshold` - Sensitivity threshold for motion detection
+    /// * `min_contour_area` - Minimum area (in pixels) to consider as motion
+    /// * `storage_dir` - Directory to store captured footage
+    /// 
+    /// # Returns
+    /// * `Result<Self>` - New MotionDetector instance or error
+    pub fn from_source(
+        source: Box<dyn FrameSource>,
+        threshold: f64,
+        min_contour_area: f64,
+        storage_dir: &str,
+    ) -> Result<Self> {
         // Initialize video storage
         let storage = storage::VideoStorage::new(storage_dir)
             .context("Failed to initialize video storage")?;
 
         Ok(Self {
-            cap,
-            background: None,
+            source,
+            background: BackgroundModel::new(&BackgroundConfig::default(), threshold)?,
+            zones: MotionZones::new(MotionConfig::default()),
             threshold,
             min_contour_area,
             storage,
-            led_pattern: None,
+            continuous: None,
+            catalogue: None,
+            manifest: None,
+            verifier: None,
+            tamper: None,
+            scene: None,
+            calibration_config: CalibrationConfig::default(),
+            classifier_config: ClassifierConfig::default(),
+            recalibration: None,
+            recalibration_attempts: 0,
+            last_recalibration_sample: None,
+            event_config: EventConfig::default(),
+            pre_roll: FrameBuffer::new(EventConfig::default().pre_roll()),
+            recorder: None,
+            quiet_frames: Vec::new(),
+            in_event: false,
+            motion_started: Duration::ZERO,
+            last_motion: Duration::ZERO,
+            event_verdict: Verdict::indeterminate(),
+            event_started_at: Local::now(),
+            last_written: Duration::ZERO,
+            event_zone: None,
+            event_boxes: Vec::new(),
+            event_peak_area: 0.0,
+            event_tamper: Vec::new(),
+            event_suspect: false,
         })
     }
 
-    /// Captures a single frame from the camera
+    /// Installs the LED verifier used to check each frame
     /// 
+    /// # Arguments
+    /// * `verifier` - Calibrated LED pattern verifier
+    pub fn set_verifier(&mut self, verifier: LedVerifier) {
+        self.verifier = Some(verifier);
+    }
+
+    /// Installs the detector for frozen, looped and stalled footage
+    pub fn set_tamper_detector(&mut self, detector: TamperDetector) {
+        self.tamper = Some(detector);
+    }
+
+    /// Installs the monitor for a covered, blurred or moved camera
+    ///
+    /// The monitor only checks frames once it has a reference scene, see
+    /// `set_scene_reference` and `load_scene_reference`.
+    pub fn set_scene_monitor(&mut self, monitor: SceneHealthMonitor) {
+        self.scene = Some(monitor);
+    }
+
+    /// Takes a frame as the reference scene of the scene monitor
+    pub fn set_scene_reference(&mut self, frame: &core::Mat) -> Result<()> {
+        match self.scene.as_mut() {
+            Some(monitor) => monitor.set_reference(frame),
+            None => Ok(()),
+        }
+    }
+
+    /// Reuses the saved reference scene of the scene monitor
+    ///
     /// # Returns
-    /// * `Result<Option<Mat>>` - Captured frame or None if capture failed
-    pub fn capture_frame(&mut self) -> Result<Option<core::Mat>> {
-        let mut frame = core::Mat::default();
-        self.cap.read(&mut frame)?;
-        
-        // Check if frame is empty (capture failed)
-        if frame.empty()? {
-            return Ok(None);
+    /// * `Result<bool>` - Whether a reference was loaded
+    pub fn load_scene_reference(&mut self, frame_width: i32, frame_height: i32) -> Result<bool> {
+        match self.scene.as_mut() {
+            Some(monitor) => monitor.load_reference(frame_width, frame_height),
+            None => Ok(false),
+        }
+    }
+
+    /// Replaces the background model, e.g. with MOG2 or KNN
+    pub fn set_background_config(&mut self, config: BackgroundConfig) -> Result<()> {
+        self.background = BackgroundModel::new(&config, self.threshold)?;
+        Ok(())
+    }
+
+    /// Sets the include and exclude zones motion is looked for in
+    pub fn set_motion_config(&mut self, config: MotionConfig) {
+        self.zones = MotionZones::new(config);
+    }
+
+    /// Installs the 24/7 recorder every frame is written to
+    ///
+    /// Motion events and tampering are marked in its segments.
+    pub fn set_continuous_recorder(&mut self, recorder: ContinuousRecorder) {
+        self.continuous = Some(recorder);
+    }
+
+    /// Installs the catalogue that saved frames and clips are recorded in
+    pub fn set_catalogue(&mut self, catalogue: Catalogue) {
+        self.catalogue = Some(catalogue);
+    }
+
+    /// Installs the hash-chained manifest that saved frames and clips are appended to
+    ///
+    /// Recordings recovered after a crash are appended straight away.
+    pub fn set_manifest(&mut self, manifest: Manifest) -> Result<()> {
+        for path in self.storage.take_recovered() {
+            manifest.record_stored(&path)?;
+        }
+        self.manifest = Some(manifest);
+        Ok(())
+    }
+
+    /// Sets the pre-roll, post-roll, minimum duration and merge gap of motion events
+    pub fn set_event_config(&mut self, config: EventConfig) {
+        self.pre_roll = FrameBuffer::new(config.pre_roll());
+        self.event_config = config;
+    }
+
+    /// Sets the calibration attempt limit, drift bound and profile location
+    pub fn set_calibration_config(&mut self, config: CalibrationConfig) {
+        self.calibration_config = config;
+    }
+
+    /// Sets how LED brightness is measured and classified by `calibrate`
+    pub fn set_classifier_config(&mut self, config: ClassifierConfig) {
+        self.classifier_config = config;
+    }
+
+    /// LED verifier in use, if calibration has been done
+    pub fn verifier(&self) -> Option<&LedVerifier> {
+        self.verifier.as_ref()
+    }
+
+    /// Searches the scene for the LEDs by their blink pattern
+    /// 
+    /// # Arguments
+    /// * `expected` - What the LED controller shows
+    /// * `config` - How long to watch and how close the match must be
+    /// 
+    /// # Returns
+    /// * `Result<Option<LedRois>>` - One region per LED, or None if they were not found
+    pub fn locate_leds(&mut self, expected: &ExpectedCode, config: &LocalizationConfig) -> Result<Option<LedRois>> {
+        let mut localizer: Option<LedLocalizer> = None;
+        let symbols = config.symbols_for(expected.payload_length());
+        let duration = SYMBOL_DURATION * symbols as u32;
+
+        println!("Searching for the LEDs over {} symbols...", symbols);
+
+        while localizer.as_ref().is_none_or(|l| l.observed() < duration) {
+            let frame = self.capture_frame()?
+                .ok_or_else(|| anyhow::anyhow!("Failed to capture frame during LED localisation"))?;
+            localizer
+                .get_or_insert_with(|| LedLocalizer::new(expected.clone(), frame.cols(), frame.rows()))
+                .add_frame(&frame, self.source.timestamp(), self.source.capture_time())?;
+        }
+
+        Ok(localizer.and_then(|l| l.finish(config.min_correlation)))
+    }
+
+    /// Calibrates the LED thresholds from the frame source and installs the verifier
+    /// 
+    /// Samples are taken once per `SAMPLE_INTERVAL` of frame time; calibration is
+    /// retried until the LEDs show enough contrast, at most `max_attempts` times.
+    /// The result is saved as the calibration profile and the last sampled
+    /// frame becomes the reference scene.
+    /// 
+    /// # Arguments
+    /// * `rois` - Regions covering the LEDs
+    /// * `expected` - What the LED controller shows
+    /// * `on_sample` - Called with every sampled frame, e.g. to show a preview;
+    ///   an error aborts the calibration
+    pub fn calibrate(
+        &mut self,
+        rois: LedRois,
+        expected: ExpectedCode,
+        mut on_sample: impl FnMut(&core::Mat, &CalibrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let max_attempts = self.calibration_config.max_attempts;
+        let mut calibration = Calibration::new(rois).with_classifier(self.classifier_config.clone());
+        let mut last_sample: Option<Duration> = None;
+        let mut frame_size = core::Size::default();
+        let mut last_frame = core::Mat::default();
+
+        println!("Starting calibration phase...");
+
+        for attempt in 1..=max_attempts {
+            while !calibration.is_complete() {
+                let frame = self.capture_frame()?
+                    .ok_or_else(|| anyhow::anyhow!("Failed to capture frame during calibration"))?;
+                let timestamp = self.source.timestamp();
+                if last_sample.is_some_and(|t| timestamp.saturating_sub(t) < verifier::SAMPLE_INTERVAL) {
+                    continue;
+                }
+                let brightnesses = calibration.add_sample(&frame)?;
+                last_sample = Some(timestamp);
+                frame_size = frame.size()?;
+
+                let progress = CalibrationProgress {
+                    sample: calibration.sample_count(),
+                    attempt,
+                    max_attempts,
+                    brightnesses,
+                };
+                on_sample(&frame, &progress)?;
+                last_frame = frame;
+            }
+
+            if let Some(thresholds) = calibration.finish() {
+                self.save_profile(frame_size.width, frame_size.height, calibration.rois(), &thresholds)?;
+                self.set_scene_reference(&last_frame)?;
+                let verifier = LedVerifier::new(calibration.rois().clone(), thresholds, expected)
+                    .with_classifier(self.classifier_config.clone());
+                self.set_verifier(verifier);
+                return Ok(());
+            }
         }
 
-        Ok(Some(frame))
+        Err(anyhow::anyhow!(
+            "Calibration failed after {} attempts: check that the LEDs are visible and the lighting is steady",
+            max_attempts
+        ))
+    }
+
+    /// Checks a saved calibration profile against the live frames
+    /// 
+    /// The profile must match the frame size, and the brightness of every LED
+    /// must stay within its calibrated range for `PROFILE_CHECK_DURATION`.
+    /// A slower loss of contrast is caught later by the drift check.
+    /// 
+    /// # Arguments
+    /// * `profile` - Profile to check
+    /// * `expected` - What the LED controller shows
+    /// 
+    /// # Returns
+    /// * `Result<bool>` - Whether the profile can be used
+    pub fn validate_profile(&mut self, profile: &CalibrationProfile, expected: &ExpectedCode) -> Result<bool> {
+        let rois = profile.led_rois();
+        let mut first_timestamp: Option<Duration> = None;
+
+        loop {
+            let frame = self.capture_frame()?
+                .ok_or_else(|| anyhow::anyhow!("Failed to capture frame while checking the calibration profile"))?;
+            if !profile.matches(frame.cols(), frame.rows(), expected.led_count()) {
+                println!("Calibration profile was made for a different frame size or LED count");
+                return Ok(false);
+            }
+
+            let brightnesses = self.classifier_config.measure(&frame, &rois)?;
+            if !profile.fits(&brightnesses, self.calibration_config.drift_tolerance) {
+                println!("LED brightness {:?} is outside the calibration profile", brightnesses);
+                return Ok(false);
+            }
+
+            let timestamp = self.source.timestamp();
+            if timestamp.saturating_sub(*first_timestamp.get_or_insert(timestamp)) >= PROFILE_CHECK_DURATION {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Saves a calibration as the profile, if profiles are enabled
+    fn save_profile(&self, frame_width: i32, frame_height: i32, rois: &LedRois, thresholds: &verifier::Thresholds) -> Result<()> {
+        if let Some(path) = self.calibration_config.profile_path() {
+            CalibrationProfile::new(frame_width, frame_height, rois, thresholds).save(path)?;
+            println!("Calibration profile saved to {}", path);
+        }
+        Ok(())
+    }
+
+    /// Captures a single frame from the frame source
+    /// 
+    /// # Returns
+    /// * `Result<Option<Mat>>` - Captured frame or None once the source is exhausted
+    pub fn capture_frame(&mut self) -> Result<Option<core::Mat>> {
+        self.source.read_frame()
+    }
+
+    /// Capture time of the most recently captured frame
+    pub fn timestamp(&self) -> Duration {
+        self.source.timestamp()
     }
 
     /// Processes a frame to detect motion
     /// 
+    /// The frame is compared with the background model, which learns every
+    /// frame at its learning rate so gradual lighting changes are absorbed.
+    /// Only the configured motion zones are searched.
+    /// 
     /// # Arguments
     /// * `frame` - The frame to analyze
     /// 
     /// # Returns
-    /// * `Result<bool>` - True if motion was detected, False otherwise
-    pub fn detect_motion(&mut self, frame: &core::Mat) -> Result<bool> {
+    /// * `Result<MotionResult>` - Motion regions with their bounding boxes, areas
+    ///   and zones, their centroid, the fraction of the frame changed and the
+    ///   foreground mask; `detected()` tells if there was motion
+    pub fn detect_motion(&mut self, frame: &core::Mat) -> Result<MotionResult> {
         // Convert to grayscale for motion analysis
         let mut gray = core::Mat::default();
-        imgproc::cvt_color(&frame, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+        imgproc::cvt_color_def(frame, &mut gray, imgproc::COLOR_BGR2GRAY)?;
         
         // Apply Gaussian blur to reduce noise
         let mut blurred = core::Mat::default();
-        imgproc::gaussian_blur(
+        imgproc::gaussian_blur_def(
             &gray, 
             &mut blurred, 
             core::Size::new(21, 21), // Kernel size
-            0.0,                     // Sigma X
-            0.0,                     // Sigma Y
-            core::BORDER_DEFAULT      // Border type
+            0.0,                     // Sigma X (Sigma Y and border type use defaults)
         )?;
 
-        // Initialize background model if this is the first frame
-        if self.background.is_none() {
-            self.background = Some(blurred.clone());
-            return Ok(false);
+        // Update the background model and get the foreground, nothing on the first frame
+        let Some(thresh) = self.background.apply(&blurred)? else {
+            return Ok(MotionResult::default());
+        };
+        
+        // Look for motion in the zones, leaving out the excluded areas and the LEDs
+        if let Some(verifier) = self.verifier.as_ref() {
+            self.zones.set_led_rois(verifier.rois());
         }
+        self.zones.detect(&thresh, self.min_contour_area)
+    }
 
-        // Compute absolute difference between current frame and background
-        let mut diff = core::Mat::default();
-        core::absdiff(&self.background.as_ref().unwrap(), &blurred, &mut diff)?;
-        
-        // Apply threshold to create binary image
-        let mut thresh = core::Mat::default();
-        imgproc::threshold(
-            &diff, 
-            &mut thresh, 
-            self.threshold,       // Threshold value
-            255.0,               // Maximum value
-            imgproc::THRESH_BINARY // Threshold type
-        )?;
-        
-        // Find contours in the thresholded image
-        let mut contours = opencv::types::VectorOfMat::new();
-        imgproc::find_contours(
-            &thresh,
-            &mut contours,
-            imgproc::RETR_EXTERNAL,      // Retrieve only external contours
-            imgproc::CHAIN_APPROX_SIMPLE,// Contour approximation method
-            core::Point::default(),      // Offset
-        )?;
+    /// Runs one frame through LED verification and motion capture
+    /// 
+    /// Every frame is fed to the LED verifier so its sampling stays in step with
+    /// the LED controller. Motion frames are saved together with the live verdict.
+    /// The event clip starts with the pre-roll, is streamed to disk as the event
+    /// goes on and is finalised once the scene has been quiet for the post-roll
+    /// and the merge gap.
+    /// 
+    /// # Arguments
+    /// * `frame` - The frame to process
+    /// 
+    /// # Returns
+    /// * `Result<FrameReport>` - Motion and verification outcome for this frame
+    pub fn process_frame(&mut self, frame: &core::Mat) -> Result<FrameReport> {
+        let verdict = self.verify_led_pattern(frame)?;
+        let mut tamper = match self.tamper.as_mut() {
+            Some(detector) => detector.process_frame(frame, self.source.timestamp(), self.source.fps())?,
+            None => Vec::new(),
+        };
+        if let Some(monitor) = self.scene.as_mut() {
+            tamper.extend(monitor.process_frame(frame, self.source.timestamp())?);
+        }
+        self.maintain_calibration(frame)?;
 
-        // Check each contour for significant motion
-        for i in 0..contours.len() {
-            let contour = contours.get(i)?;
-            let area = imgproc::contour_area(&contour, false)?;
-            
-            if area > self.min_contour_area {
-                // Update background model
-                self.background = Some(blurred.clone());
-                return Ok(true);
+        // Detect motion in the frame
+        let motion = self.detect_motion(frame)?;
+        let motion_detected = motion.detected();
+
+        let timestamp = self.source.timestamp();
+        let buffered = BufferedFrame { timestamp, frame: frame.clone(), verdict };
+
+        // Record the frame continuously and mark any tampering in the segment
+        if let Some(recorder) = self.continuous.as_mut() {
+            recorder.write(frame, timestamp, self.source.fps())?;
+            recorder.record_tamper(timestamp, &tamper)?;
+        }
+
+        if motion_detected {
+            if !self.in_event {
+                // Start of new motion event, with the frames from just before it
+                let pre_roll = self.pre_roll.take(timestamp);
+                self.recorder = Some(self.storage.start_event(frame.size()?, self.source.fps())?);
+                self.event_verdict = pre_roll.iter().fold(verdict, |worst, f| worst.worst(f.verdict));
+                self.event_started_at = self.wall_clock(pre_roll.first().map_or(timestamp, |f| f.timestamp));
+
+                let zone = motion.zone.as_deref().unwrap_or(WHOLE_FRAME_ZONE);
+                let event = format!(
+                    "Motion detected in zone {} ({} regions, {:.1}% of the frame changed) - starting capture with {} pre-roll frames",
+                    zone,
+                    motion.regions.len(),
+                    motion.changed_fraction * 100.0,
+                    pre_roll.len()
+                );
+                logging::log_verified_event(&event, &self.event_verdict)?;
+                self.mark(timestamp, &format!("{} - {}", event, self.event_verdict))?;
+                self.in_event = true;
+                self.event_zone = motion.zone.clone();
+                self.event_boxes.clear();
+                self.event_peak_area = 0.0;
+                self.event_tamper.clear();
+                self.event_suspect = false;
+                self.motion_started = timestamp;
+
+                for buffered in pre_roll {
+                    self.write_event_frame(buffered)?;
+                }
             }
+            self.last_motion = timestamp;
+            if motion.total_area() > self.event_peak_area {
+                self.event_peak_area = motion.total_area();
+                self.event_boxes = motion.regions.iter().map(|region| region.bbox).collect();
+            }
+
+            // Save the frame
+            let path = self.storage.save_frame(frame, &format!("motion_{}", verdict.status.label()))?;
+            if let Some(manifest) = self.manifest.as_ref() {
+                manifest.record_stored(&path)?;
+            }
+            if let Some(catalogue) = self.catalogue.as_ref() {
+                let now = Local::now();
+                let mut record = EventRecord::new(&path, FileKind::Frame, "motion", catalogue.camera_id(), now, now)?;
+                record.frames = 1;
+                record.zone = motion.zone.clone();
+                record.motion_boxes = motion.regions.iter().map(|region| rect_box(region.bbox)).collect();
+                record.verdict = Some(verdict.into());
+                record.tamper = tamper.iter().map(|event| event.to_string()).collect();
+                catalogue.record(&record)?;
+            }
+
+            // The quiet frames before this one are part of the event after all
+            for buffered in std::mem::take(&mut self.quiet_frames) {
+                self.write_event_frame(buffered)?;
+            }
+            self.write_event_frame(buffered)?;
+        } else if self.in_event {
+            // Write the quiet frames within the post-roll, hold the rest in case the
+            // event ends before motion resumes
+            self.quiet_frames.push(buffered);
+            let cutoff = self.last_motion + self.event_config.post_roll();
+            let within = self.quiet_frames.iter().take_while(|f| f.timestamp <= cutoff).count();
+            for buffered in self.quiet_frames.drain(..within).collect::<Vec<_>>() {
+                self.write_event_frame(buffered)?;
+            }
+
+            if timestamp.saturating_sub(self.last_motion) >= self.event_config.quiet_timeout() {
+                self.end_event()?;
+            }
+        } else {
+            self.pre_roll.push(buffered);
+        }
+        if self.in_event {
+            self.event_tamper.extend(tamper.iter().map(|event| event.to_string()));
+            self.event_suspect |= matches!(
+                self.verification_state(),
+                VerificationState::Suspect | VerificationState::Tampered
+            );
         }
 
-        Ok(false)
+        Ok(FrameReport {
+            motion_detected,
+            verdict,
+            state: self.verification_state(),
+            in_event: self.in_event,
+            tamper,
+            motion,
+        })
     }
 
-    /// Monitors the video feed continuously for motion events
+    /// Writes a frame to the current event clip, logging if it lowers the event's verdict
+    fn write_event_frame(&mut self, buffered: BufferedFrame) -> Result<()> {
+        // Verified only if every frame written was verified
+        let worst = self.event_verdict.worst(buffered.verdict);
+        if worst.status != self.event_verdict.status {
+            logging::log_verified_event("Verification degraded during motion event", &worst)?;
+        }
+        self.event_verdict = worst;
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.write(&buffered.frame)?;
+        }
+        self.last_written = buffered.timestamp;
+        Ok(())
+    }
+
+    /// Wall-clock time a frame with the given source timestamp was captured,
+    /// counted back from the current frame
+    fn wall_clock(&self, timestamp: Duration) -> DateTime<Local> {
+        let age = self.source.timestamp().saturating_sub(timestamp);
+        Local::now() - chrono::Duration::from_std(age).unwrap_or(chrono::Duration::zero())
+    }
+
+    /// Closes the current motion event and finalises its clip
+    ///
+    /// Quiet frames past the post-roll were never written and go back to the
+    /// pre-roll buffer; the clip of an event with less motion than the minimum
+    /// duration is deleted.
+    fn end_event(&mut self) -> Result<()> {
+        self.in_event = false;
+        for buffered in std::mem::take(&mut self.quiet_frames) {
+            self.pre_roll.push(buffered);
+        }
+        let Some(recorder) = self.recorder.take() else {
+            return Ok(());
+        };
+
+        let duration = self.last_motion.saturating_sub(self.motion_started);
+        if duration < self.event_config.min_duration() {
+            let event = format!(
+                "Motion event dropped after {:.1}s of motion, shorter than {:.1}s",
+                duration.as_secs_f64(),
+                self.event_config.min_duration().as_secs_f64()
+            );
+            logging::log_verified_event(&event, &self.event_verdict)?;
+            self.mark(self.last_motion, &format!("{} - {}", event, self.event_verdict))?;
+            return recorder.discard();
+        }
+
+        let event = format!("Motion event ended after {:.1}s of motion, {} frames", duration.as_secs_f64(), recorder.frames());
+        logging::log_verified_event(&event, &self.event_verdict)?;
+        self.mark(self.last_motion, &format!("{} - {}", event, self.event_verdict))?;
+
+        let event_type = format!("motion_event_{}", self.event_verdict.status.label());
+        let frames = recorder.frames();
+        let path = recorder.finish(&event_type)?;
+        if let Some(manifest) = self.manifest.as_ref() {
+            manifest.record_stored(&path)?;
+        }
+        if !self.event_tamper.is_empty() || self.event_suspect {
+            // Keep the clip whatever the retention limits
+            retention::flag_evidence(std::path::Path::new(&path))?;
+        }
+        if let Some(catalogue) = self.catalogue.as_ref() {
+            let end = self.wall_clock(self.last_written);
+            let mut record = EventRecord::new(&path, FileKind::Clip, "motion_event", catalogue.camera_id(), self.event_started_at, end)?;
+            record.frames = frames;
+            record.zone = self.event_zone.clone();
+            record.motion_boxes = self.event_boxes.iter().map(|&bbox| rect_box(bbox)).collect();
+            record.verdict = Some(self.event_verdict.into());
+            record.tamper = self.event_tamper.clone();
+            catalogue.record(&record)?;
+        }
+        println!("Saved motion event: {}", path);
+        Ok(())
+    }
+
+    /// Marks an event in the continuous recording, if there is one
+    fn mark(&mut self, timestamp: Duration, event: &str) -> Result<()> {
+        if let Some(recorder) = self.continuous.as_mut() {
+            recorder.mark(timestamp, event)?;
+        }
+        Ok(())
+    }
+
+    /// Closes any motion event still in progress and the current segment,
+    /// e.g. before shutting down
+    pub fn finish(&mut self) -> Result<()> {
+        if self.in_event {
+            self.end_event()?;
+        }
+        if let Some(recorder) = self.continuous.as_mut() {
+            recorder.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Monitors the video feed for motion events until the source is exhausted
     pub fn monitor(&mut self) -> Result<()> {
-        let mut event_frames = Vec::new();
-        let mut in_event = false;
-        
-        loop {
-            if let Some(frame) = self.capture_frame()? {
-                // Detect motion in the frame
-                let motion_detected = self.detect_motion(&frame)?;
-                
-                if motion_detected {
-                    if !in_event {
-                        // Start of new motion event
-                        logging::log_event("Motion detected - starting capture")?;
-                        in_event = true;
+        while let Some(frame) = self.capture_frame()? {
+            self.process_frame(&frame)?;
+        }
+        self.finish()
+    }
+
+    /// Recalibrates in the background when the LED contrast drifts
+    /// 
+    /// When the measured contrast moves more than `drift_tolerance` away from
+    /// the calibrated one, calibration samples are taken from the live frames
+    /// alongside normal monitoring. A successful recalibration replaces the
+    /// thresholds and the saved profile; after `max_attempts` failures in a row
+    /// no new attempt is made until the contrast recovers.
+    fn maintain_calibration(&mut self, frame: &core::Mat) -> Result<()> {
+        let Some(verifier) = self.verifier.as_mut() else {
+            return Ok(());
+        };
+        let timestamp = self.source.timestamp();
+
+        let Some(calibration) = self.recalibration.as_mut() else {
+            match verifier.contrast_drift() {
+                Some(drift) if drift > self.calibration_config.drift_tolerance => {
+                    if self.recalibration_attempts < self.calibration_config.max_attempts {
+                        logging::log_event(&format!("LED contrast drifted by {:.0}% - recalibrating", drift * 100.0))?;
+                        self.recalibration = Some(
+                            Calibration::new(verifier.rois().clone()).with_classifier(verifier.classifier_config().clone()),
+                        );
+                        self.last_recalibration_sample = None;
                     }
-                    
-                    // Save the frame
-                    let saved_path = self.storage.save_frame(&frame, "motion")?;
-                    event_frames.push(frame.clone());
-                    
-                    // Here we would add LED pattern verification later
-                    // let verified = self.verify_led_pattern(&frame)?;
-                    // logging::log_verified_event("Motion captured", verified);
-                } else if in_event {
-                    // End of motion event
-                    logging::log_event("Motion event ended")?;
-                    
-                    // Save the collected frames as a video
-                    if !event_frames.is_empty() {
-                        self.storage.save_video(&event_frames, "motion_event", 15.0)?;
-                        event_frames.clear();
+                }
+                Some(_) => self.recalibration_attempts = 0,
+                None => {}
+            }
+            return Ok(());
+        };
+
+        if self.last_recalibration_sample.is_some_and(|t| timestamp.saturating_sub(t) < verifier::SAMPLE_INTERVAL) {
+            return Ok(());
+        }
+        calibration.add_sample(frame)?;
+        self.last_recalibration_sample = Some(timestamp);
+
+        if calibration.is_complete() {
+            match calibration.finish() {
+                Some(thresholds) => {
+                    logging::log_event("LED recalibration succeeded")?;
+                    if let Some(path) = self.calibration_config.profile_path() {
+                        CalibrationProfile::new(frame.cols(), frame.rows(), verifier.rois(), &thresholds).save(path)?;
                     }
-                    
-                    in_event = false;
+                    verifier.set_thresholds(thresholds);
+                    self.recalibration_attempts = 0;
+                }
+                None => {
+                    self.recalibration_attempts += 1;
+                    logging::log_event(&format!(
+                        "LED recalibration failed (attempt {}/{})",
+                        self.recalibration_attempts, self.calibration_config.max_attempts
+                    ))?;
                 }
             }
+            self.recalibration = None;
         }
+        Ok(())
+    }
+
+    /// Current verification state, Pending until a verifier is installed
+    pub fn verification_state(&self) -> VerificationState {
+        self.verifier.as_ref().map_or(VerificationState::Pending, LedVerifier::state)
     }
 
-    /// Placeholder for future LED pattern verification
-    fn verify_led_pattern(&self, frame: &core::Mat) -> Result<bool> {
-        // TODO: Implement LED pattern detection and verification
-        Ok(true)
+    /// Checks the LED pattern visible in the frame
+    /// 
+    /// Without a calibrated verifier the footage cannot be vouched for, so it is
+    /// reported as indeterminate.
+    fn verify_led_pattern(&mut self, frame: &core::Mat) -> Result<Verdict> {
+        match self.verifier.as_mut() {
+            Some(verifier) => verifier.process_frame(frame, self.source.timestamp(), self.source.capture_time()),
+            None => Ok(Verdict::indeterminate()),
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Rectangle as stored in the catalogue: x, y, width, height
+fn rect_box(rect: core::Rect) -> [i32; 4] {
+    [rect.x, rect.y, rect.width, rect.height]
+}
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::controller::camera::verdict::Verdict;
+
+// Extension appended to a file's name for its sidecar: <file>.json
+pub const SIDECAR_EXTENSION: &str = "json";
+
+/// What kind of file a record describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    Frame,   // Single JPEG of a motion frame
+    Clip,    // Video of a motion event
+    Segment, // Video segment of the continuous recording
+}
+
+impl FileKind {
+    /// Lower-case name, as stored in the records
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileKind::Frame => "frame",
+            FileKind::Clip => "clip",
+            FileKind::Segment => "segment",
+        }
+    }
+}
+
+/// Verification verdict as stored in the catalogue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerdictRecord {
+    pub status: String,  // verified, unverified or indeterminate
+    pub confidence: f64,
+}
+
+impl From<Verdict> for VerdictRecord {
+    fn from(verdict: Verdict) -> Self {
+        Self {
+            status: verdict.status.label().to_string(),
+            confidence: verdict.confidence,
+        }
+    }
+}
+
+/// Metadata of one saved file, written to its sidecar and to the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub file: String,                   // Path of the file as saved
+    pub kind: FileKind,
+    pub event_type: String,             // Why the file was saved, e.g. motion_event
+    pub camera_id: String,
+    pub start: DateTime<Local>,         // Wall-clock time of the first frame
+    pub end: DateTime<Local>,           // Wall-clock time of the last frame
+    pub duration_s: f64,
+    pub frames: usize,
+    pub zone: Option<String>,           // Motion zone that triggered
+    pub motion_boxes: Vec<[i32; 4]>,    // Motion regions as x, y, width, height
+    pub verdict: Option<VerdictRecord>, // LED verification verdict, the worst one over a clip
+    pub tamper: Vec<String>,            // Tampering detected while the file was recorded
+    pub sha256: String,                 // Hash of the file contents
+}
+
+impl EventRecord {
+    /// Describes a saved file, hashing its contents
+    ///
+    /// # Arguments
+    /// * `file` - Path of the saved file
+    /// * `kind` - Frame, clip or segment
+    /// * `event_type` - Why the file was saved
+    /// * `camera_id` - Camera the footage comes from
+    /// * `start` / `end` - Wall-clock times of the first and last frame
+    pub fn new(
+        file: &str,
+        kind: FileKind,
+        event_type: &str,
+        camera_id: &str,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Self> {
+        Ok(Self {
+            file: file.to_string(),
+            kind,
+            event_type: event_type.to_string(),
+            camera_id: camera_id.to_string(),
+            start,
+            end,
+            duration_s: (end - start).num_milliseconds().max(0) as f64 / 1000.0,
+            frames: 0,
+            zone: None,
+            motion_boxes: Vec::new(),
+            verdict: None,
+            tamper: Vec::new(),
+            sha256: hash_file(Path::new(file))?,
+        })
+    }
+}
+
+/// Filter of a catalogue query; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub from: Option<DateTime<Local>>, // Files recorded at or after this time
+    pub to: Option<DateTime<Local>>,   // Files recorded at or before this time
+    pub kind: Option<FileKind>,
+    pub event_type: Option<String>,
+    pub verdict: Option<String>,       // Verdict status label
+    pub camera_id: Option<String>,
+}
+
+impl EventQuery {
+    /// Whether a record matches the filter; a file matches a time range it overlaps
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        self.from.is_none_or(|from| record.end >= from)
+            && self.to.is_none_or(|to| record.start <= to)
+            && self.kind.is_none_or(|kind| record.kind == kind)
+            && self.event_type.as_ref().is_none_or(|event_type| &record.event_type == event_type)
+            && self.verdict.as_ref().is_none_or(|verdict| {
+                record.verdict.as_ref().is_some_and(|v| v.status.eq_ignore_ascii_case(verdict))
+            })
+            && self.camera_id.as_ref().is_none_or(|camera_id| &record.camera_id == camera_id)
+    }
+}
+
+/// Settings of the event catalogue
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CatalogueConfig {
+    pub enabled: bool,
+    pub camera_id: String,  // Name of this camera in the records
+    pub index_path: String, // Append-only JSON Lines index of all records
+}
+
+impl Default for CatalogueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            camera_id: "camera".to_string(),
+            index_path: "event_index.jsonl".to_string(),
+        }
+    }
+}
+
+/// Catalogue of the saved footage
+///
+/// Every saved frame, clip and segment gets a JSON sidecar next to it, and
+/// the same record is appended as one line to the index, so events can be
+/// found by time, type and verdict without parsing file names. The index is
+/// only ever appended to; records of deleted files stay in it.
+#[derive(Debug, Clone)]
+pub struct Catalogue {
+    config: CatalogueConfig,
+}
+
+impl Catalogue {
+    /// Creates a catalogue writing to the configured index
+    pub fn new(config: CatalogueConfig) -> Self {
+        Self { config }
+    }
+
+    /// Name of this camera in the records
+    pub fn camera_id(&self) -> &str {
+        &self.config.camera_id
+    }
+
+    /// Writes the sidecar of a file and appends its record to the index
+    pub fn record(&self, record: &EventRecord) -> Result<()> {
+        let sidecar = sidecar_path(Path::new(&record.file));
+        std::fs::write(&sidecar, serde_json::to_string_pretty(record)?)
+            .context(format!("Failed to write sidecar: {}", sidecar.display()))?;
+
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.index_path)
+            .context(format!("Failed to open event index: {}", self.config.index_path))?;
+        writeln!(index, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Finds the records matching a query, oldest first
+    ///
+    /// Lines that cannot be read, e.g. one cut short by a crash, are skipped.
+    ///
+    /// # Returns
+    /// * `Result<Vec<EventRecord>>` - Matching records in index order
+    pub fn query(&self, query: &EventQuery) -> Result<Vec<EventRecord>> {
+        if !Path::new(&self.config.index_path).exists() {
+            return Ok(Vec::new());
+        }
+        let index = File::open(&self.config.index_path)
+            .context(format!("Failed to open event index: {}", self.config.index_path))?;
+
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(index).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<EventRecord>(&line) {
+                Ok(record) if query.matches(&record) => records.push(record),
+                Ok(_) => {}
+                Err(e) => println!("Skipping unreadable line {} of {}: {}", number + 1, self.config.index_path, e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Path of the sidecar of a file: the file name with `.json` appended
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".");
+    sidecar.push(SIDECAR_EXTENSION);
+    PathBuf::from(sidecar)
+}
+
+/// SHA-256 of a file's contents, as hex
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).context(format!("Failed to open {} for hashing", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Parses a query time, either RFC 3339 or local `YYYY-mm-dd HH:MM:SS`
+pub fn parse_time(text: &str) -> Result<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .context(format!("Invalid time '{}', expected RFC 3339 or YYYY-mm-dd HH:MM:SS", text))?;
+    Local.from_local_datetime(&naive)
+        .single()
+        .ok_or_else(|| anyhow::anyhow!("Ambiguous local time: {}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> DateTime<Local> {
+        parse_time(text).unwrap()
+    }
+
+    /// A verified motion clip recorded from 10:00 to 10:05
+    fn clip() -> EventRecord {
+        EventRecord {
+            file: "footage/video_20261017_100000_motion_event_verified.avi".to_string(),
+            kind: FileKind::Clip,
+            event_type: "motion_event".to_string(),
+            camera_id: "front".to_string(),
+            start: time("2026-10-17 10:00:00"),
+            end: time("2026-10-17 10:05:00"),
+            duration_s: 300.0,
+            frames: 9000,
+            zone: None,
+            motion_boxes: Vec::new(),
+            verdict: Some(VerdictRecord { status: "verified".to_string(), confidence: 0.9 }),
+            tamper: Vec::new(),
+            sha256: String::new(),
+        }
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(EventQuery::default().matches(&clip()));
+    }
+
+    #[test]
+    fn time_range_matches_files_it_overlaps() {
+        let range = |from: Option<&str>, to: Option<&str>| EventQuery {
+            from: from.map(time),
+            to: to.map(time),
+            ..EventQuery::default()
+        };
+        assert!(range(Some("2026-10-17 10:03:00"), None).matches(&clip()));
+        assert!(range(Some("2026-10-17 10:05:00"), None).matches(&clip()));
+        assert!(!range(Some("2026-10-17 10:05:01"), None).matches(&clip()));
+        assert!(range(None, Some("2026-10-17 10:00:00")).matches(&clip()));
+        assert!(!range(None, Some("2026-10-17 09:59:59")).matches(&clip()));
+        assert!(range(Some("2026-10-17 10:01:00"), Some("2026-10-17 10:02:00")).matches(&clip()));
+        assert!(!range(Some("2026-10-17 11:00:00"), Some("2026-10-17 12:00:00")).matches(&clip()));
+    }
+
+    #[test]
+    fn kind_and_type_must_match_exactly() {
+        assert!(EventQuery { kind: Some(FileKind::Clip), ..EventQuery::default() }.matches(&clip()));
+        assert!(!EventQuery { kind: Some(FileKind::Segment), ..EventQuery::default() }.matches(&clip()));
+        assert!(EventQuery { event_type: Some("motion_event".to_string()), ..EventQuery::default() }.matches(&clip()));
+        assert!(!EventQuery { event_type: Some("motion".to_string()), ..EventQuery::default() }.matches(&clip()));
+    }
+
+    #[test]
+    fn verdict_matches_its_status_in_any_case() {
+        let verdict = |status: &str| EventQuery { verdict: Some(status.to_string()), ..EventQuery::default() };
+        assert!(verdict("verified").matches(&clip()));
+        assert!(verdict("VERIFIED").matches(&clip()));
+        assert!(!verdict("unverified").matches(&clip()));
+
+        // Files without a verdict match no verdict filter
+        let unchecked = EventRecord { verdict: None, ..clip() };
+        assert!(!verdict("indeterminate").matches(&unchecked));
+        assert!(EventQuery::default().matches(&unchecked));
+    }
+
+    #[test]
+    fn camera_must_match() {
+        assert!(EventQuery { camera_id: Some("front".to_string()), ..EventQuery::default() }.matches(&clip()));
+        assert!(!EventQuery { camera_id: Some("back".to_string()), ..EventQuery::default() }.matches(&clip()));
+    }
+
+    #[test]
+    fn times_parse_from_rfc3339_or_local_time() {
+        let rfc3339 = parse_time("2026-10-17T08:00:00Z").unwrap();
+        assert_eq!(rfc3339.timestamp(), 1_792_224_000);
+        let local = parse_time("2026-10-17 08:00:00").unwrap();
+        assert_eq!(local.format("%Y-%m-%d %H:%M:%S").to_string(), "2026-10-17 08:00:00");
+    }
+
+    #[test]
+    fn malformed_times_are_rejected() {
+        for text in ["", "yesterday", "2026-10-17", "2026-10-17 08:00", "2026-13-01 00:00:00", "2026-10-17T08:00:00", "17/10/2026 08:00:00"] {
+            assert!(parse_time(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use opencv::{
+    core::{self, Mat, Rect},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use crate::controller::camera::verifier::{LedRange, LedRois};
+
+/// Colour of an LED, selecting which channels its brightness is measured on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LedColor {
+    #[default]
+    Blue,  // Channel 0 of BGR, what the verifier has always measured
+    Green,
+    Red,
+    White, // Mean of all three channels
+}
+
+impl LedColor {
+    /// Brightness of an LED of this colour inside `roi`
+    ///
+    /// With `excess`, the mean of the other two channels is subtracted from a
+    /// single colour, so white ambient light largely cancels out and e.g. a
+    /// red PWR LED is not confused with a green ACT LED next to it.
+    pub fn brightness(&self, frame: &Mat, roi: Rect, excess: bool) -> Result<f64> {
+        let region = Mat::roi(frame, roi)?;
+        let mean = core::mean(&region, &Mat::default())?;
+        let (b, g, r) = (mean.0[0], mean.0[1], mean.0[2]);
+
+        Ok(match (self, excess) {
+            (LedColor::White, _) => (b + g + r) / 3.0,
+            (LedColor::Blue, false) => b,
+            (LedColor::Green, false) => g,
+            (LedColor::Red, false) => r,
+            (LedColor::Blue, true) => b - (g + r) / 2.0,
+            (LedColor::Green, true) => g - (b + r) / 2.0,
+            (LedColor::Red, true) => r - (b + g) / 2.0,
+        })
+    }
+}
+
+/// How LED brightness is measured and turned into symbol levels
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    pub hysteresis: f64,      // Extra distance past a level boundary needed to switch, as a fraction of the level spacing
+    pub baseline_rate: f64,   // Weight of each OFF frame in the baseline average (0 = fixed baseline)
+    pub colors: Vec<LedColor>,// Colour of each LED in channel order; missing entries are blue
+    pub color_excess: bool,   // Subtract the other channels from single-colour LEDs
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            hysteresis: 0.1,
+            baseline_rate: 0.02,
+            colors: Vec::new(),
+            color_excess: false,
+        }
+    }
+}
+
+impl ClassifierConfig {
+    /// Colour of LED `led`
+    pub fn color(&self, led: usize) -> LedColor {
+        self.colors.get(led).copied().unwrap_or_default()
+    }
+
+    /// Measures the brightness of every LED in a frame
+    pub fn measure(&self, frame: &Mat, rois: &LedRois) -> Result<Vec<f64>> {
+        rois.rects.iter()
+            .enumerate()
+            .map(|(led, &roi)| self.color(led).brightness(frame, roi, self.color_excess))
+            .collect()
+    }
+}
+
+/// Turns the brightness of one LED into symbol levels
+///
+/// The level only changes once the brightness is past the boundary to the
+/// neighbouring level by the hysteresis margin, so a brightness hovering
+/// around a boundary does not flicker; for on/off LEDs this gives separate
+/// switch-on and switch-off thresholds. Levels are measured from a baseline
+/// that follows the OFF brightness as an exponential moving average, so slow
+/// changes in ambient light do not shift every level.
+#[derive(Debug, Clone)]
+pub struct LedClassifier {
+    contrast: f64,      // Calibrated brightness difference between off and fully lit
+    baseline: f64,      // Current OFF brightness
+    levels: u8,
+    level: u8,          // Level of the previous frame
+    hysteresis: f64,
+    baseline_rate: f64,
+}
+
+impl LedClassifier {
+    /// Creates a classifier from a calibrated brightness range
+    pub fn new(range: &LedRange, levels: u8, config: &ClassifierConfig) -> Self {
+        Self {
+            contrast: range.contrast(),
+            baseline: range.off,
+            levels: levels.max(2),
+            level: 0,
+            hysteresis: config.hysteresis.max(0.0),
+            baseline_rate: config.baseline_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Brightness in level units above the baseline
+    fn position(&self, brightness: f64) -> f64 {
+        let spacing = self.contrast / (self.levels - 1) as f64;
+        if spacing <= 0.0 {
+            return 0.0;
+        }
+        (brightness - self.baseline) / spacing
+    }
+
+    /// Classifies one brightness measurement
+    pub fn classify(&mut self, brightness: f64) -> u8 {
+        let top = (self.levels - 1) as f64;
+        let position = self.position(brightness);
+
+        if (position - self.level as f64).abs() > 0.5 + self.hysteresis {
+            self.level = position.round().clamp(0.0, top) as u8;
+        }
+        if self.level == 0 {
+            self.baseline += self.baseline_rate * (brightness - self.baseline);
+        }
+        self.level
+    }
+
+    /// How clearly a brightness falls on one level (1.0 = exactly on a level,
+    /// 0.0 = halfway between two)
+    pub fn margin(&self, brightness: f64) -> f64 {
+        let top = (self.levels - 1) as f64;
+        let position = self.position(brightness);
+        if position <= 0.0 || position >= top {
+            return 1.0;
+        }
+        (1.0 - 2.0 * (position - position.round()).abs()).clamp(0.0, 1.0)
+    }
+
+    /// Current OFF brightness
+    pub fn baseline(&self) -> f64 {
+        self.baseline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier(off: f64, on: f64, levels: u8, hysteresis: f64, baseline_rate: f64) -> LedClassifier {
+        let config = ClassifierConfig { hysteresis, baseline_rate, ..ClassifierConfig::default() };
+        LedClassifier::new(&LedRange { off, on, noise: 0.0 }, levels, &config)
+    }
+
+    #[test]
+    fn level_holds_inside_the_hysteresis_band() {
+        // Switches on above 60 and off below 40
+        let mut led = classifier(0.0, 100.0, 2, 0.1, 0.0);
+        let levels: Vec<u8> = [55.0, 59.0, 61.0, 45.0, 41.0, 55.0, 39.0, 59.0]
+            .into_iter()
+            .map(|brightness| led.classify(brightness))
+            .collect();
+        assert_eq!(levels, vec![0, 0, 1, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn multi_level_steps_need_the_margin_too() {
+        // Levels 0, 1, 2, 3 every 30 brightness
+        let mut led = classifier(0.0, 90.0, 4, 0.1, 0.0);
+        assert_eq!(led.classify(90.0), 3);
+        assert_eq!(led.classify(44.0), 1);
+        assert_eq!(led.classify(47.0), 1);
+        assert_eq!(led.classify(50.0), 2);
+        assert_eq!(led.classify(0.0), 0);
+    }
+
+    #[test]
+    fn baseline_follows_the_off_brightness() {
+        let mut led = classifier(10.0, 110.0, 2, 0.1, 0.5);
+        for _ in 0..30 {
+            assert_eq!(led.classify(20.0), 0);
+        }
+        assert!((led.baseline() - 20.0).abs() < 1e-3);
+
+        // Lit frames leave the baseline alone
+        assert_eq!(led.classify(120.0), 1);
+        assert!((led.baseline() - 20.0).abs() < 1e-3);
+
+        // Levels are measured from the new baseline, which 59 above is not enough to leave OFF
+        assert_eq!(led.classify(30.0), 0);
+        let baseline = led.baseline();
+        assert_eq!(led.classify(baseline + 59.0), 0);
+    }
+
+    #[test]
+    fn fixed_baseline_does_not_move() {
+        let mut led = classifier(10.0, 110.0, 2, 0.1, 0.0);
+        for _ in 0..10 {
+            led.classify(30.0);
+        }
+        assert_eq!(led.baseline(), 10.0);
+    }
+
+    #[test]
+    fn margin_is_highest_on_a_level_and_lowest_between_two() {
+        let led = classifier(0.0, 100.0, 2, 0.1, 0.0);
+        assert_eq!(led.margin(0.0), 1.0);
+        assert_eq!(led.margin(100.0), 1.0);
+        assert!(led.margin(50.0) < 1e-9);
+        assert!((led.margin(25.0) - 0.5).abs() < 1e-9);
+    }
+}
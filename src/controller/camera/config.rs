@@ -0,0 +1,148 @@
+use opencv::core::Rect;
+use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+use crate::controller::camera::background::BackgroundConfig;
+use crate::controller::camera::catalogue::CatalogueConfig;
+use crate::controller::camera::classifier::ClassifierConfig;
+use crate::controller::camera::event::EventConfig;
+use crate::controller::camera::manifest::ManifestConfig;
+use crate::controller::camera::policy::VerificationPolicy;
+use crate::controller::camera::recording::RecordingConfig;
+use crate::controller::camera::retention::RetentionConfig;
+use crate::controller::camera::scene::SceneHealthConfig;
+use crate::controller::camera::tamper::TamperConfig;
+use crate::controller::camera::verifier::LedRois;
+use crate::controller::camera::zones::MotionConfig;
+use crate::controller::security::protocol;
+
+// Default location of the camera parameters, relative to the repository root
+pub const DEFAULT_CAMERA_CONFIG_PATH: &str = "src/tuning/camera_parameters.toml";
+
+/// A rectangle in frame pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RectConfig {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<RectConfig> for Rect {
+    fn from(rect: RectConfig) -> Self {
+        Rect::new(rect.x, rect.y, rect.width, rect.height)
+    }
+}
+
+impl From<Rect> for RectConfig {
+    fn from(rect: Rect) -> Self {
+        Self { x: rect.x, y: rect.y, width: rect.width, height: rect.height }
+    }
+}
+
+/// Settings of the automatic LED localisation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LocalizationConfig {
+    pub enabled: bool,        // Search the scene for the LEDs before calibrating
+    pub symbols: usize,       // How many LED symbols to watch, 0 for two whole LED frames
+    pub min_correlation: f64, // Weakest match with the blink pattern accepted as an LED
+}
+
+impl Default for LocalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            symbols: 0,
+            min_correlation: 0.5,
+        }
+    }
+}
+
+impl LocalizationConfig {
+    /// LED symbols to watch for frames of `payload_length` payload symbols
+    pub fn symbols_for(&self, payload_length: usize) -> usize {
+        match self.symbols {
+            0 => 2 * protocol::frame_length(payload_length),
+            symbols => symbols,
+        }
+    }
+}
+
+/// Settings of the LED calibration and its saved profile
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    pub profile_path: String,  // Where the calibration profile is saved, empty to disable
+    pub max_attempts: usize,   // Failed calibration attempts before giving up
+    pub drift_tolerance: f64,  // Relative contrast change that triggers a recalibration
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            profile_path: "calibration_profile.toml".to_string(),
+            max_attempts: 5,
+            drift_tolerance: 0.4,
+        }
+    }
+}
+
+impl CalibrationConfig {
+    /// Path of the calibration profile, if profiles are enabled
+    pub fn profile_path(&self) -> Option<&str> {
+        Some(self.profile_path.as_str()).filter(|path| !path.is_empty())
+    }
+}
+
+/// Camera-side parameters
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub localization: LocalizationConfig,
+    pub calibration: CalibrationConfig,
+    pub classifier: ClassifierConfig,
+    pub verification: VerificationPolicy,
+    pub tamper: TamperConfig,
+    pub scene: SceneHealthConfig,
+    pub background: BackgroundConfig,
+    pub motion: MotionConfig,
+    pub event: EventConfig,
+    pub recording: RecordingConfig,
+    pub retention: RetentionConfig,
+    pub catalogue: CatalogueConfig,
+    pub manifest: ManifestConfig,
+    pub led_rois: Vec<RectConfig>, // Fallback LED regions, one per LED channel
+}
+
+impl CameraConfig {
+    /// Loads the camera parameters from a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the parameters file
+    ///
+    /// # Returns
+    /// * `Result<CameraConfig>` - Parameters or error
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .context(format!("Failed to read camera parameters: {}", path))?;
+        toml::from_str(&text).context(format!("Failed to parse camera parameters: {}", path))
+    }
+
+    /// LED regions given in the config, if there is one per LED
+    pub fn configured_rois(&self, led_count: usize) -> Option<LedRois> {
+        if self.led_rois.is_empty() {
+            return None;
+        }
+        if self.led_rois.len() != led_count {
+            println!(
+                "Ignoring configured LED ROIs: {} given for {} LEDs",
+                self.led_rois.len(),
+                led_count
+            );
+            return None;
+        }
+        Some(LedRois {
+            rects: self.led_rois.iter().map(|&rect| rect.into()).collect(),
+        })
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+use crate::controller::security::protocol::{self, PREAMBLE, SYMBOL_DURATION};
+
+// Largest tolerated difference between the LED clock and the camera clock,
+// as a fraction of the nominal symbol duration
+pub const MAX_CLOCK_DRIFT: f64 = 0.1;
+
+// Weight of each new measurement in the symbol period estimate
+const PERIOD_SMOOTHING: f64 = 0.2;
+
+// Runs shorter than this fraction of a symbol are treated as glitches, e.g.
+// LEDs switching on slightly different frames
+const GLITCH_FRACTION: f64 = 0.5;
+
+/// Payload decoded from one LED frame
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    pub payload: Vec<Vec<u8>>,   // Payload symbols, one pattern per LED
+    pub start: Duration,         // Source timestamp at which the preamble started
+    pub start_time: SystemTime,  // Wall-clock time at which the preamble started
+    pub symbol_period: Duration, // Symbol duration measured on the camera clock
+    pub timing_error: f64,       // Mean edge misalignment, as a fraction of a symbol
+}
+
+/// A decoded symbol: the level of every LED during one symbol period
+struct Symbol {
+    states: Vec<u8>,
+    start: Duration,
+    start_time: SystemTime,
+}
+
+/// Where the decoder is within the LED frame structure
+enum FrameState {
+    Hunting,
+    Payload {
+        start: Duration,
+        start_time: SystemTime,
+        symbols: Vec<Vec<u8>>,
+    },
+}
+
+/// Frame-synchronous LED decoder
+///
+/// Every camera frame is classified and fed in. Symbol boundaries are recovered
+/// from the edges between runs of identical LED states, and the symbol period
+/// is re-estimated at every edge, so a slow drift between the LED controller's
+/// clock and the camera clock is followed instead of accumulating. Payloads are
+/// framed by locking onto the sync preamble, whose opening run of top-level
+/// symbols never occurs inside a payload.
+pub struct SymbolDecoder {
+    payload_length: usize,
+    levels: u8,                     // Brightness levels per symbol
+    period: f64,                    // Current symbol period estimate, in seconds
+    run_states: Option<Vec<u8>>,    // LED states of the current run
+    run_start: Duration,            // When the current run started
+    run_start_time: SystemTime,     // Wall-clock start of the current run
+    run_emitted: usize,             // Symbols already emitted from the current run
+    history: VecDeque<Symbol>,      // Recent symbols, searched for the preamble
+    state: FrameState,
+    edge_errors: Vec<f64>,          // Edge misalignments within the current frame
+}
+
+impl SymbolDecoder {
+    /// Creates a decoder for frames carrying `payload_length` symbols of
+    /// `levels` brightness levels
+    pub fn new(payload_length: usize, levels: u8) -> Self {
+        Self {
+            payload_length,
+            levels,
+            period: SYMBOL_DURATION.as_secs_f64(),
+            run_states: None,
+            run_start: Duration::ZERO,
+            run_start_time: SystemTime::UNIX_EPOCH,
+            run_emitted: 0,
+            history: VecDeque::with_capacity(PREAMBLE.len() + 1),
+            state: FrameState::Hunting,
+            edge_errors: Vec::new(),
+        }
+    }
+
+    /// Whether the decoder has locked onto a preamble and is reading a payload
+    pub fn is_locked(&self) -> bool {
+        matches!(self.state, FrameState::Payload { .. })
+    }
+
+    /// Current estimate of the symbol duration on the camera clock
+    pub fn symbol_period(&self) -> Duration {
+        Duration::from_secs_f64(self.period)
+    }
+
+    /// Feeds the LED levels classified from one camera frame
+    ///
+    /// # Arguments
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    /// * `capture_time` - Wall-clock capture time of the frame
+    /// * `states` - Level of every LED in the frame
+    ///
+    /// # Returns
+    /// * `Option<DecodedFrame>` - A payload, when this frame completed one
+    pub fn push(&mut self, timestamp: Duration, capture_time: SystemTime, states: &[u8]) -> Option<DecodedFrame> {
+        let mut decoded = None;
+
+        match &self.run_states {
+            None => self.start_run(timestamp, capture_time, states),
+            Some(run) if run.as_slice() != states => {
+                let duration = timestamp.saturating_sub(self.run_start).as_secs_f64();
+
+                if duration < self.period * GLITCH_FRACTION && self.run_emitted == 0 {
+                    // Too short to be a symbol: fold it into the run that follows
+                    self.run_states = Some(states.to_vec());
+                    return None;
+                }
+
+                // The run covered a whole number of symbols; use the edge to
+                // correct the period estimate within the drift bound
+                let count = (duration / self.period).round().max(1.0);
+                self.edge_errors.push(((duration - count * self.period) / self.period).abs());
+
+                let nominal = SYMBOL_DURATION.as_secs_f64();
+                self.period += PERIOD_SMOOTHING * (duration / count - self.period);
+                self.period = self.period.clamp(nominal * (1.0 - MAX_CLOCK_DRIFT), nominal * (1.0 + MAX_CLOCK_DRIFT));
+
+                while self.run_emitted < count as usize {
+                    decoded = decoded.or(self.emit_symbol());
+                }
+                self.start_run(timestamp, capture_time, states);
+            }
+            Some(_) => {}
+        }
+
+        // Emit symbols of a long run as soon as they are certain, half a
+        // symbol into the next one
+        let elapsed = timestamp.saturating_sub(self.run_start).as_secs_f64();
+        while elapsed >= (self.run_emitted as f64 + 1.5) * self.period {
+            decoded = decoded.or(self.emit_symbol());
+        }
+
+        decoded
+    }
+
+    fn start_run(&mut self, timestamp: Duration, capture_time: SystemTime, states: &[u8]) {
+        self.run_states = Some(states.to_vec());
+        self.run_start = timestamp;
+        self.run_start_time = capture_time;
+        self.run_emitted = 0;
+    }
+
+    /// Emits the next symbol of the current run
+    fn emit_symbol(&mut self) -> Option<DecodedFrame> {
+        let offset = Duration::from_secs_f64(self.period * self.run_emitted as f64);
+        let symbol = Symbol {
+            states: self.run_states.clone().unwrap_or_default(),
+            start: self.run_start + offset,
+            start_time: self.run_start_time + offset,
+        };
+        self.run_emitted += 1;
+
+        self.on_symbol(symbol)
+    }
+
+    /// Advances the frame state machine by one symbol
+    fn on_symbol(&mut self, symbol: Symbol) -> Option<DecodedFrame> {
+        match &mut self.state {
+            FrameState::Hunting => {
+                self.history.push_back(symbol);
+                while self.history.len() > PREAMBLE.len() {
+                    self.history.pop_front();
+                }
+
+                let levels = self.levels;
+                let preamble_found = self.history.len() == PREAMBLE.len()
+                    && self.history.iter().enumerate().all(|(i, s)| {
+                        let expected = protocol::preamble_level(i, levels);
+                        s.states.iter().all(|&state| state == expected)
+                    });
+
+                if preamble_found {
+                    let first = &self.history[0];
+                    self.state = FrameState::Payload {
+                        start: first.start,
+                        start_time: first.start_time,
+                        symbols: Vec::with_capacity(self.payload_length),
+                    };
+                    self.history.clear();
+                    self.edge_errors.clear();
+                }
+                None
+            }
+            FrameState::Payload { start, start_time, symbols } => {
+                symbols.push(symbol.states);
+                if symbols.len() < self.payload_length {
+                    return None;
+                }
+
+                // Transpose time-ordered symbols into one pattern per LED
+                let led_count = symbols[0].len();
+                let payload = (0..led_count)
+                    .map(|led| symbols.iter().map(|states| states[led]).collect())
+                    .collect();
+
+                let frame = DecodedFrame {
+                    payload,
+                    start: *start,
+                    start_time: *start_time,
+                    symbol_period: Duration::from_secs_f64(self.period),
+                    timing_error: if self.edge_errors.is_empty() {
+                        0.0
+                    } else {
+                        self.edge_errors.iter().sum::<f64>() / self.edge_errors.len() as f64
+                    },
+                };
+                self.state = FrameState::Hunting;
+                Some(frame)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FPS: f64 = 10.0;
+
+    // Two LEDs, never both lit for three symbols in a row
+    fn payload() -> Vec<Vec<u8>> {
+        vec![vec![0, 1, 0, 1, 1, 0], vec![1, 1, 0, 0, 1, 0]]
+    }
+
+    /// Time-ordered LED states of `frames` consecutive frames, then the next preamble
+    fn symbols(frames: usize) -> Vec<Vec<u8>> {
+        let payload = payload();
+        let frame = (0..protocol::frame_length(6)).map(|index| protocol::frame_symbol(&payload, index, 2));
+        let mut symbols: Vec<Vec<u8>> = frame.clone().cycle().take(frames * protocol::frame_length(6)).collect();
+        symbols.extend(frame.take(PREAMBLE.len()));
+        symbols
+    }
+
+    /// Films the symbols at `FPS`, each shown for `symbol_s` seconds; LED `i`
+    /// switches `lags[i]` seconds late
+    fn film(decoder: &mut SymbolDecoder, symbols: &[Vec<u8>], symbol_s: f64, lags: &[f64]) -> Vec<DecodedFrame> {
+        let frames = (symbols.len() as f64 * symbol_s * FPS) as usize;
+        (0..frames)
+            .filter_map(|i| {
+                let time = i as f64 / FPS;
+                let states: Vec<u8> = lags.iter().enumerate()
+                    .map(|(led, lag)| {
+                        let index = (((time - lag).max(0.0)) / symbol_s) as usize;
+                        symbols[index.min(symbols.len() - 1)][led]
+                    })
+                    .collect();
+                let timestamp = Duration::from_secs_f64(time);
+                decoder.push(timestamp, SystemTime::UNIX_EPOCH + timestamp, &states)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clean_frames_decode_from_their_preamble() {
+        let mut decoder = SymbolDecoder::new(6, 2);
+        let decoded = film(&mut decoder, &symbols(2), 1.0, &[0.0, 0.0]);
+
+        assert_eq!(decoded.len(), 2);
+        for (frame, start) in decoded.iter().zip([0.0, 10.0]) {
+            assert_eq!(frame.payload, payload());
+            assert!((frame.start.as_secs_f64() - start).abs() < 1e-6);
+            assert!(frame.timing_error < 1e-6);
+        }
+    }
+
+    #[test]
+    fn decoding_starts_at_the_next_preamble_when_joined_mid_frame() {
+        let mut decoder = SymbolDecoder::new(6, 2);
+        // Joined three symbols into the payload of the first frame
+        let joined = &symbols(2)[PREAMBLE.len() + 3..];
+        let decoded = film(&mut decoder, joined, 1.0, &[0.0, 0.0]);
+
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].payload, payload());
+        assert!((decoded[0].start.as_secs_f64() - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn drift_between_the_clocks_is_followed() {
+        let mut decoder = SymbolDecoder::new(6, 2);
+        // The LED clock runs 5% slow against the camera's
+        let decoded = film(&mut decoder, &symbols(3), 1.05, &[0.0, 0.0]);
+
+        assert_eq!(decoded.len(), 3);
+        assert!(decoded.iter().all(|frame| frame.payload == payload()));
+        assert!((decoded[2].start.as_secs_f64() - 21.0).abs() < 0.15);
+        assert!((decoder.symbol_period().as_secs_f64() - 1.05).abs() < 0.03);
+    }
+
+    #[test]
+    fn drift_beyond_the_bound_is_clamped() {
+        let mut decoder = SymbolDecoder::new(6, 2);
+        film(&mut decoder, &symbols(2), 1.5, &[0.0, 0.0]);
+        let nominal = SYMBOL_DURATION.as_secs_f64();
+        assert!(decoder.symbol_period().as_secs_f64() <= nominal * (1.0 + MAX_CLOCK_DRIFT) + 1e-9);
+    }
+
+    #[test]
+    fn single_frame_glitch_at_an_edge_is_ignored() {
+        let mut decoder = SymbolDecoder::new(6, 2);
+        // The second LED switches one camera frame after the first
+        let decoded = film(&mut decoder, &symbols(2), 1.0, &[0.0, 1.0 / FPS]);
+
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded.iter().all(|frame| frame.payload == payload()));
+        assert!((decoder.symbol_period().as_secs_f64() - 1.0).abs() < 0.02);
+    }
+}
@@ -0,0 +1,109 @@
+use opencv::core::Mat;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::controller::camera::verdict::Verdict;
+
+/// How motion events are cut from the footage
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EventConfig {
+    pub pre_roll_ms: u64,     // Footage kept from before the motion started
+    pub post_roll_ms: u64,    // Footage kept after the motion stopped
+    pub min_duration_ms: u64, // Shorter bursts of motion are dropped as noise
+    pub merge_gap_ms: u64,    // Quiet time after which motion starts a new event rather than continuing the current one
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        Self {
+            pre_roll_ms: 3000,
+            post_roll_ms: 3000,
+            min_duration_ms: 500,
+            merge_gap_ms: 5000,
+        }
+    }
+}
+
+impl EventConfig {
+    /// Footage kept from before the motion started
+    pub fn pre_roll(&self) -> Duration {
+        Duration::from_millis(self.pre_roll_ms)
+    }
+
+    /// Footage kept after the motion stopped
+    pub fn post_roll(&self) -> Duration {
+        Duration::from_millis(self.post_roll_ms)
+    }
+
+    /// Shortest burst of motion that makes an event
+    pub fn min_duration(&self) -> Duration {
+        Duration::from_millis(self.min_duration_ms)
+    }
+
+    /// Quiet time after which an event ends: the post-roll or the merge gap,
+    /// whichever is longer
+    pub fn quiet_timeout(&self) -> Duration {
+        Duration::from_millis(self.post_roll_ms.max(self.merge_gap_ms))
+    }
+}
+
+/// A frame held for a motion event, with its capture time and verdict
+#[derive(Debug, Clone)]
+pub struct BufferedFrame {
+    pub timestamp: Duration, // Capture time on the source's clock
+    pub frame: Mat,
+    pub verdict: Verdict,    // LED verification verdict at the time of the frame
+}
+
+/// Ring buffer of the most recent frames, used as the pre-roll of an event
+pub struct FrameBuffer {
+    span: Duration,                  // How far back frames are kept
+    frames: VecDeque<BufferedFrame>, // Oldest first
+}
+
+impl FrameBuffer {
+    /// Creates an empty buffer keeping `span` worth of frames
+    pub fn new(span: Duration) -> Self {
+        Self {
+            span,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Adds a frame, dropping the ones that have fallen out of the span
+    pub fn push(&mut self, frame: BufferedFrame) {
+        let now = frame.timestamp;
+        self.frames.push_back(frame);
+        self.drop_before(now);
+    }
+
+    /// Takes the frames within the span before `now`, emptying the buffer
+    ///
+    /// # Arguments
+    /// * `now` - Capture time of the frame that starts the event
+    ///
+    /// # Returns
+    /// * `Vec<BufferedFrame>` - The pre-roll frames, oldest first
+    pub fn take(&mut self, now: Duration) -> Vec<BufferedFrame> {
+        self.drop_before(now);
+        self.frames.drain(..).collect()
+    }
+
+    /// Number of frames held
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frame is held
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn drop_before(&mut self, now: Duration) {
+        // Frames from a clock that went backwards are dropped too
+        while self.frames.front().is_some_and(|f| f.timestamp > now || now - f.timestamp > self.span) {
+            self.frames.pop_front();
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use opencv::{
+    core::{Mat, Rect, Size},
+    imgproc,
+    prelude::*,
+};
+use anyhow::Result;
+use std::time::{Duration, SystemTime};
+use crate::controller::camera::verifier::{ExpectedCode, LedRois};
+use crate::controller::security::protocol::{self, SYMBOL_DURATION};
+
+// Side of the square pixel blocks the scene is searched in
+pub const CELL_SIZE: i32 = 8;
+
+// Cells correlating at least this fraction of the best cell belong to the LED
+const REGION_FRACTION: f64 = 0.7;
+
+// Largest distance, in cells, from the best cell that can still belong to the LED
+const MAX_REGION_CELLS: i32 = 4;
+
+/// Running sums correlating every cell with one expected LED signal
+struct SignalStats {
+    sum: f64,
+    sum_sq: f64,
+    cross: Vec<f64>, // Sum of cell intensity times signal, per cell
+}
+
+/// Finds the LEDs in the scene from their blink pattern
+///
+/// Every frame is reduced to a grid of cells, and the intensity of each cell
+/// over time is correlated with the levels each LED is expected to show.
+/// The offset between the LED clock and the camera clock is not known yet, so
+/// the expected signal is tried at several offsets in half-symbol steps (every
+/// frame phase for a fixed payload, up to the clock skew for a rolling code)
+/// and the offset that matches all LEDs best wins.
+pub struct LedLocalizer {
+    expected: ExpectedCode,
+    frame_size: Size,
+    grid: Size,                     // Number of cells across and down
+    offsets: Vec<i64>,              // Candidate LED clock offsets, in half symbols
+    signals: Vec<Vec<SignalStats>>, // Per offset, per LED
+    cell_sum: Vec<f64>,
+    cell_sum_sq: Vec<f64>,
+    samples: usize,
+    first_timestamp: Option<Duration>,
+    last_timestamp: Duration,
+}
+
+impl LedLocalizer {
+    /// Creates a localizer for frames of the given size
+    ///
+    /// # Arguments
+    /// * `expected` - What the LED controller sends
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    pub fn new(expected: ExpectedCode, width: i32, height: i32) -> Self {
+        let grid = Size::new((width / CELL_SIZE).max(1), (height / CELL_SIZE).max(1));
+        let cells = (grid.width * grid.height) as usize;
+
+        let offsets: Vec<i64> = match &expected {
+            ExpectedCode::Fixed(patterns) => (0..2 * protocol::frame_length(patterns.len()) as i64).collect(),
+            ExpectedCode::Rolling { max_skew, .. } => {
+                let steps = (max_skew.as_millis() * 2 / SYMBOL_DURATION.as_millis()) as i64;
+                (-steps..=steps).collect()
+            }
+        };
+
+        let signals = offsets.iter()
+            .map(|_| {
+                (0..expected.led_count())
+                    .map(|_| SignalStats { sum: 0.0, sum_sq: 0.0, cross: vec![0.0; cells] })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            expected,
+            frame_size: Size::new(width, height),
+            grid,
+            offsets,
+            signals,
+            cell_sum: vec![0.0; cells],
+            cell_sum_sq: vec![0.0; cells],
+            samples: 0,
+            first_timestamp: None,
+            last_timestamp: Duration::ZERO,
+        }
+    }
+
+    /// How much source time has been observed so far
+    pub fn observed(&self) -> Duration {
+        self.first_timestamp.map_or(Duration::ZERO, |first| self.last_timestamp.saturating_sub(first))
+    }
+
+    /// Adds one frame to the correlation
+    ///
+    /// # Arguments
+    /// * `frame` - Camera frame (BGR)
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    /// * `capture_time` - Wall-clock capture time of the frame
+    pub fn add_frame(&mut self, frame: &Mat, timestamp: Duration, capture_time: SystemTime) -> Result<()> {
+        let mut gray = Mat::default();
+        imgproc::cvt_color_def(frame, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+        let mut cells = Mat::default();
+        imgproc::resize(&gray, &mut cells, self.grid, 0.0, 0.0, imgproc::INTER_AREA)?;
+        let intensities: Vec<f64> = cells.data_typed::<u8>()?.iter().map(|&v| v as f64).collect();
+
+        for (i, &x) in intensities.iter().enumerate() {
+            self.cell_sum[i] += x;
+            self.cell_sum_sq[i] += x * x;
+        }
+
+        let half_symbol = SYMBOL_DURATION / 2;
+        for (&offset, stats) in self.offsets.iter().zip(self.signals.iter_mut()) {
+            let shift = half_symbol * offset.unsigned_abs() as u32;
+            let time = if offset >= 0 { capture_time + shift } else { capture_time - shift };
+            let levels = self.expected.symbols_at(time)?;
+
+            for (led, stat) in stats.iter_mut().enumerate() {
+                let y = levels.get(led).copied().unwrap_or(0) as f64;
+                stat.sum += y;
+                stat.sum_sq += y * y;
+                if y != 0.0 {
+                    for (cross, &x) in stat.cross.iter_mut().zip(&intensities) {
+                        *cross += x * y;
+                    }
+                }
+            }
+        }
+
+        self.samples += 1;
+        self.first_timestamp.get_or_insert(timestamp);
+        self.last_timestamp = timestamp;
+        Ok(())
+    }
+
+    /// Picks the LED regions from the frames seen so far
+    ///
+    /// # Arguments
+    /// * `min_correlation` - Weakest match with the blink pattern accepted as an LED
+    ///
+    /// # Returns
+    /// * `Option<LedRois>` - One region per LED, or None if any LED was not found
+    pub fn finish(&self, min_correlation: f64) -> Option<LedRois> {
+        if self.samples == 0 {
+            return None;
+        }
+        let cells = self.cell_sum.len();
+
+        // Best cell of every LED at every offset; an offset is as good as its weakest LED
+        let mut best: Option<(f64, usize, Vec<(usize, f64)>)> = None;
+        for (index, stats) in self.signals.iter().enumerate() {
+            let peaks: Vec<(usize, f64)> = stats.iter()
+                .map(|stat| {
+                    (0..cells)
+                        .map(|cell| (cell, self.correlation(stat, cell)))
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .unwrap_or((0, 0.0))
+                })
+                .collect();
+            let score = peaks.iter().map(|peak| peak.1).fold(f64::MAX, f64::min);
+
+            if best.as_ref().is_none_or(|b| score > b.0) {
+                best = Some((score, index, peaks));
+            }
+        }
+
+        let (score, index, peaks) = best?;
+        println!(
+            "LED localisation: clock offset {:+.1} symbols, weakest LED correlation = {:.2}",
+            self.offsets[index] as f64 / 2.0,
+            score
+        );
+        if score < min_correlation {
+            println!("LED localisation failed: correlation below {:.2}", min_correlation);
+            return None;
+        }
+
+        let rects: Vec<Rect> = peaks.iter()
+            .zip(&self.signals[index])
+            .map(|(&(cell, correlation), stat)| self.region(stat, cell, correlation))
+            .collect();
+        for (i, rect) in rects.iter().enumerate() {
+            println!("LED{} found at {:?}", i + 1, rect);
+        }
+
+        Some(LedRois { rects })
+    }
+
+    /// Pearson correlation between a cell's intensity and an LED signal
+    fn correlation(&self, stat: &SignalStats, cell: usize) -> f64 {
+        let n = self.samples as f64;
+        let covariance = n * stat.cross[cell] - self.cell_sum[cell] * stat.sum;
+        let variance_x = n * self.cell_sum_sq[cell] - self.cell_sum[cell] * self.cell_sum[cell];
+        let variance_y = n * stat.sum_sq - stat.sum * stat.sum;
+
+        if variance_x <= 0.0 || variance_y <= 0.0 {
+            return 0.0;
+        }
+        covariance / (variance_x * variance_y).sqrt()
+    }
+
+    /// Frame region covering the cells around `cell` that follow the same signal
+    fn region(&self, stat: &SignalStats, cell: usize, correlation: f64) -> Rect {
+        let (cx, cy) = (cell as i32 % self.grid.width, cell as i32 / self.grid.width);
+        let (mut x0, mut y0, mut x1, mut y1) = (cx, cy, cx, cy);
+
+        for y in (cy - MAX_REGION_CELLS).max(0)..=(cy + MAX_REGION_CELLS).min(self.grid.height - 1) {
+            for x in (cx - MAX_REGION_CELLS).max(0)..=(cx + MAX_REGION_CELLS).min(self.grid.width - 1) {
+                if self.correlation(stat, (y * self.grid.width + x) as usize) >= correlation * REGION_FRACTION {
+                    x0 = x0.min(x);
+                    y0 = y0.min(y);
+                    x1 = x1.max(x);
+                    y1 = y1.max(y);
+                }
+            }
+        }
+
+        // Cells back to frame pixels
+        let scale_x = self.frame_size.width as f64 / self.grid.width as f64;
+        let scale_y = self.frame_size.height as f64 / self.grid.height as f64;
+        let left = (x0 as f64 * scale_x) as i32;
+        let top = (y0 as f64 * scale_y) as i32;
+        let right = ((x1 + 1) as f64 * scale_x) as i32;
+        let bottom = ((y1 + 1) as f64 * scale_y) as i32;
+
+        Rect::new(left, top, right - left, bottom - top)
+    }
+}
@@ -2,6 +2,7 @@ use chrono::Local;
 use std::fs::OpenOptions;
 use std::io::Write;
 use anyhow::Result;
+use crate::controller::camera::verdict::Verdict;
 
 /// Logs security events with timestamps to a text file
 /// 
@@ -38,11 +39,10 @@ pub fn log_event(event: &str) -> Result<()> {
 /// 
 /// # Arguments
 /// * `event` - The security event description
-/// * `verified` - Whether LED pattern verification passed
+/// * `verdict` - LED pattern verification verdict and its confidence
 /// 
 /// # Returns
 /// * `Result<()>` - Ok if successful, Err if file operations fail
-pub fn log_verified_event(event: &str, verified: bool) -> Result<()> {
-    let status = if verified { "VERIFIED" } else { "TAMPER DETECTED" };
-    log_event(&format!("{} - {}", event, status))
+pub fn log_verified_event(event: &str, verdict: &Verdict) -> Result<()> {
+    log_event(&format!("{} - {}", event, verdict))
 }
\ No newline at end of file
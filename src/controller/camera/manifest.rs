@@ -0,0 +1,580 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use fs2::FileExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::controller::camera::catalogue::hash_file;
+use crate::controller::camera::retention::FOOTAGE_EXTENSIONS;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Previous hash of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// Bytes read from the end of the manifest to find its last entry
+const TAIL_CHUNK: u64 = 4096;
+
+// Separates the manifest key from other keys derived from the shared secret
+const KEY_LABEL: &[u8] = b"footage manifest";
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestAction {
+    Stored, // The file was written
+    Pruned, // The file was deleted by retention
+}
+
+impl ManifestAction {
+    /// Lower-case name, as stored in the manifest
+    pub fn label(&self) -> &'static str {
+        match self {
+            ManifestAction::Stored => "stored",
+            ManifestAction::Pruned => "pruned",
+        }
+    }
+}
+
+/// One line of the manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub sequence: u64,             // Position in the chain, from 0
+    pub timestamp: DateTime<Local>,
+    pub action: ManifestAction,
+    pub file: String,              // Path of the file as saved
+    pub sha256: String,            // Hash of the file contents when it was stored
+    pub size: u64,
+    pub prev_hash: String,         // `entry_hash` of the previous entry
+    pub entry_hash: String,        // HMAC of this entry's fields and `prev_hash`
+}
+
+impl ManifestEntry {
+    /// HMAC chaining this entry to the previous one
+    ///
+    /// # Arguments
+    /// * `key` - Manifest key derived from the shared secret
+    pub fn compute_hash(&self, key: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            self.sequence,
+            self.timestamp.to_rfc3339(),
+            self.action.label(),
+            self.file,
+            self.sha256,
+            self.size,
+            self.prev_hash
+        ).as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Head of a chain ending with this entry
+    pub fn head(&self) -> ManifestHead {
+        ManifestHead { sequence: self.sequence, entry_hash: self.entry_hash.clone() }
+    }
+}
+
+/// Last entry of the chain, kept apart from the manifest to detect truncation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestHead {
+    pub sequence: u64,
+    pub entry_hash: String,
+}
+
+impl fmt::Display for ManifestHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.sequence, self.entry_hash)
+    }
+}
+
+impl std::str::FromStr for ManifestHead {
+    type Err = anyhow::Error;
+
+    /// Parses a head written as `<sequence>:<entry hash>`
+    fn from_str(text: &str) -> Result<Self> {
+        let (sequence, entry_hash) = text.trim().split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid manifest head '{}', expected <sequence>:<hash>", text.trim()))?;
+        Ok(Self {
+            sequence: sequence.parse().context(format!("Invalid manifest head sequence: {}", sequence))?,
+            entry_hash: entry_hash.to_string(),
+        })
+    }
+}
+
+/// Settings of the footage manifest
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ManifestConfig {
+    pub enabled: bool,
+    pub path: String,      // Append-only JSON Lines manifest
+    pub head_path: String, // Latest head of the chain, best kept on other storage than the footage
+}
+
+impl Default for ManifestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "footage_manifest.jsonl".to_string(),
+            head_path: "footage_manifest.head".to_string(),
+        }
+    }
+}
+
+/// Something `verify` found wrong with the footage or the manifest
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestProblem {
+    Unreadable { line: usize },                    // A line is not a manifest entry
+    SequenceGap { expected: u64, found: u64 },     // Entries were deleted or reordered
+    BrokenChain { sequence: u64 },                 // The entry does not follow from the previous one
+    EntryModified { sequence: u64 },               // The entry was edited after it was written
+    Missing { file: String },                      // A stored file was deleted without being pruned
+    Modified { file: String },                     // A stored file no longer has its recorded hash
+    Unlisted { file: String },                     // Footage on disk that was never stored through the manifest
+    HeadMissing { path: String },                  // The head file is gone while the manifest has entries
+    Truncated { head: u64, last: Option<u64> },    // The manifest ends before the recorded head
+    HeadMismatch { sequence: u64 },                // The entry at the recorded head is not the one recorded
+    HeadBehind { head: u64, last: u64 },           // Entries were appended without moving the head
+}
+
+impl fmt::Display for ManifestProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestProblem::Unreadable { line } => write!(f, "line {} is not a manifest entry", line),
+            ManifestProblem::SequenceGap { expected, found } => {
+                write!(f, "entry {} follows entry {}: entries deleted or reordered", found, expected.saturating_sub(1))
+            }
+            ManifestProblem::BrokenChain { sequence } => write!(f, "entry {} does not chain to the previous entry", sequence),
+            ManifestProblem::EntryModified { sequence } => write!(f, "entry {} was modified", sequence),
+            ManifestProblem::Missing { file } => write!(f, "{} was deleted", file),
+            ManifestProblem::Modified { file } => write!(f, "{} was modified", file),
+            ManifestProblem::Unlisted { file } => write!(f, "{} is not in the manifest", file),
+            ManifestProblem::HeadMissing { path } => write!(f, "head file {} is missing", path),
+            ManifestProblem::Truncated { head, last: Some(last) } => {
+                write!(f, "manifest ends at entry {} but the head is entry {}: entries were cut off", last, head)
+            }
+            ManifestProblem::Truncated { head, last: None } => write!(f, "manifest is empty but the head is entry {}", head),
+            ManifestProblem::HeadMismatch { sequence } => write!(f, "entry {} does not match the recorded head", sequence),
+            ManifestProblem::HeadBehind { head, last } => {
+                write!(f, "head file records entry {} but the manifest ends at entry {}", head, last)
+            }
+        }
+    }
+}
+
+/// Outcome of verifying the manifest against the footage
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub entries: usize,             // Entries read
+    pub files: usize,               // Stored files checked on disk
+    pub head: Option<ManifestHead>, // Head of the chain as read, to keep for later checks
+    pub problems: Vec<ManifestProblem>,
+}
+
+impl VerifyReport {
+    /// Whether the footage and the manifest are intact
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Hash-chained manifest of the stored footage
+///
+/// Every stored frame, clip and segment is appended with its SHA-256, and
+/// every entry carries an HMAC, keyed with the shared secret, over its fields
+/// and the HMAC of the entry before it. Without the secret the manifest cannot
+/// be edited, shortened in the middle, reordered or rebuilt without breaking
+/// the chain. The head of the chain is also written to `head_path` and can be
+/// exported from `verify`, so cutting entries off the end is caught as well.
+/// `verify` then checks each file still on disk against its recorded hash,
+/// that files only disappeared through retention (`pruned` entries), and that
+/// no footage was added behind the manifest's back. Appends take an exclusive
+/// lock on the file, so the pipeline and the retention janitor can share it.
+#[derive(Clone)]
+pub struct Manifest {
+    config: ManifestConfig,
+    key: Vec<u8>, // Derived from the shared secret
+}
+
+impl fmt::Debug for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Manifest").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+impl Manifest {
+    /// Creates a manifest writing to the configured path
+    ///
+    /// # Arguments
+    /// * `config` - Manifest and head locations
+    /// * `secret` - Secret shared with the LED controller, which keys the chain
+    pub fn new(config: ManifestConfig, secret: &[u8]) -> Self {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(KEY_LABEL);
+        Self { config, key: mac.finalize().into_bytes().to_vec() }
+    }
+
+    /// Appends a newly stored file, hashing its contents
+    pub fn record_stored(&self, file: &str) -> Result<ManifestEntry> {
+        let path = Path::new(file);
+        let size = std::fs::metadata(path).context(format!("Failed to read {}", file))?.len();
+        self.append(ManifestAction::Stored, file, hash_file(path)?, size)
+    }
+
+    /// Appends a file deleted by retention, with the hash it was stored with
+    ///
+    /// # Arguments
+    /// * `file` - Path of the deleted file
+    /// * `stored` - Its latest `stored` entry, from `stored_files`
+    pub fn record_pruned(&self, file: &str, stored: Option<&ManifestEntry>) -> Result<ManifestEntry> {
+        let (sha256, size) = stored.map_or((String::new(), 0), |entry| (entry.sha256.clone(), entry.size));
+        self.append(ManifestAction::Pruned, file, sha256, size)
+    }
+
+    /// Latest `stored` entry of every file not pruned since, so many pruned
+    /// files can be recorded with a single read of the manifest
+    pub fn stored_files(&self) -> Result<HashMap<String, ManifestEntry>> {
+        let mut files = HashMap::new();
+        for entry in self.entries()? {
+            match entry.action {
+                ManifestAction::Stored => files.insert(entry.file.clone(), entry),
+                ManifestAction::Pruned => files.remove(&entry.file),
+            };
+        }
+        Ok(files)
+    }
+
+    /// Head of the chain as last written to `head_path`
+    pub fn saved_head(&self) -> Result<Option<ManifestHead>> {
+        if !Path::new(&self.config.head_path).exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&self.config.head_path)
+            .context(format!("Failed to read manifest head: {}", self.config.head_path))?;
+        Ok(Some(text.parse()?))
+    }
+
+    /// Checks the chain and the footage
+    ///
+    /// # Arguments
+    /// * `directories` - Where footage is stored, searched for unlisted files
+    /// * `exported` - A head exported earlier, e.g. kept off the camera; the
+    ///   chain must still contain it
+    ///
+    /// # Returns
+    /// * `Result<VerifyReport>` - Every problem found
+    pub fn verify(&self, directories: &[PathBuf], exported: Option<&ManifestHead>) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let mut latest: HashMap<String, ManifestEntry> = HashMap::new();
+        let mut previous_hash = GENESIS_HASH.to_string();
+        let mut expected = 0;
+        let mut hashes: HashMap<u64, String> = HashMap::new();
+
+        for (number, line) in self.lines()?.into_iter().enumerate() {
+            let Ok(entry) = serde_json::from_str::<ManifestEntry>(&line) else {
+                report.problems.push(ManifestProblem::Unreadable { line: number + 1 });
+                continue;
+            };
+            report.entries += 1;
+
+            if entry.sequence != expected {
+                report.problems.push(ManifestProblem::SequenceGap { expected, found: entry.sequence });
+            }
+            if entry.entry_hash != entry.compute_hash(&self.key) {
+                report.problems.push(ManifestProblem::EntryModified { sequence: entry.sequence });
+            } else if entry.prev_hash != previous_hash {
+                report.problems.push(ManifestProblem::BrokenChain { sequence: entry.sequence });
+            }
+            expected = entry.sequence + 1;
+            previous_hash = entry.entry_hash.clone();
+            hashes.insert(entry.sequence, entry.entry_hash.clone());
+            report.head = Some(entry.head());
+            latest.insert(entry.file.clone(), entry);
+        }
+
+        // The saved head must be the last entry; an exported one may be older
+        match self.saved_head()? {
+            Some(saved) => {
+                self.check_head(&saved, &hashes, &mut report);
+                if let Some(last) = report.head.as_ref().filter(|last| last.sequence > saved.sequence) {
+                    report.problems.push(ManifestProblem::HeadBehind { head: saved.sequence, last: last.sequence });
+                }
+            }
+            None if report.entries > 0 => {
+                report.problems.push(ManifestProblem::HeadMissing { path: self.config.head_path.clone() });
+            }
+            None => {}
+        }
+        if let Some(exported) = exported {
+            self.check_head(exported, &hashes, &mut report);
+        }
+
+        // Files as of their latest entry
+        let mut files: Vec<&ManifestEntry> = latest.values().collect();
+        files.sort_by_key(|entry| entry.sequence);
+        for entry in files.iter().filter(|entry| entry.action == ManifestAction::Stored) {
+            report.files += 1;
+            let path = Path::new(&entry.file);
+            if !path.exists() {
+                report.problems.push(ManifestProblem::Missing { file: entry.file.clone() });
+            } else if hash_file(path)? != entry.sha256 {
+                report.problems.push(ManifestProblem::Modified { file: entry.file.clone() });
+            }
+        }
+
+        let listed: HashSet<&str> = latest.keys().map(String::as_str).collect();
+        for file in footage_files(directories)? {
+            if !listed.contains(file.as_str()) {
+                report.problems.push(ManifestProblem::Unlisted { file });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks that the chain reaches a recorded head and contains it
+    fn check_head(&self, head: &ManifestHead, hashes: &HashMap<u64, String>, report: &mut VerifyReport) {
+        let last = report.head.as_ref().map(|last| last.sequence);
+        if last.is_none_or(|last| last < head.sequence) {
+            report.problems.push(ManifestProblem::Truncated { head: head.sequence, last });
+        } else if hashes.get(&head.sequence) != Some(&head.entry_hash) {
+            report.problems.push(ManifestProblem::HeadMismatch { sequence: head.sequence });
+        }
+    }
+
+    /// All readable entries, in order
+    fn entries(&self) -> Result<Vec<ManifestEntry>> {
+        Ok(self.lines()?
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    fn lines(&self) -> Result<Vec<String>> {
+        if !Path::new(&self.config.path).exists() {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&self.config.path)
+            .context(format!("Failed to open manifest: {}", self.config.path))?;
+        let mut lines = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Appends an entry chained to the last one, under an exclusive lock, and
+    /// moves the saved head to it
+    fn append(&self, action: ManifestAction, file: &str, sha256: String, size: u64) -> Result<ManifestEntry> {
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.config.path)
+            .context(format!("Failed to open manifest: {}", self.config.path))?;
+        manifest.lock_exclusive().context("Failed to lock manifest")?;
+
+        let result = (|| -> Result<ManifestEntry> {
+            let last = last_line(&mut manifest)?
+                .map(|line| serde_json::from_str::<ManifestEntry>(&line))
+                .transpose()
+                .context(format!("Last entry of {} is unreadable", self.config.path))?;
+
+            let mut entry = ManifestEntry {
+                sequence: last.as_ref().map_or(0, |last| last.sequence + 1),
+                timestamp: Local::now(),
+                action,
+                file: file.to_string(),
+                sha256,
+                size,
+                prev_hash: last.map_or(GENESIS_HASH.to_string(), |last| last.entry_hash),
+                entry_hash: String::new(),
+            };
+            entry.entry_hash = entry.compute_hash(&self.key);
+            writeln!(manifest, "{}", serde_json::to_string(&entry)?)?;
+            manifest.sync_data()?;
+            self.save_head(&entry.head())?;
+            Ok(entry)
+        })();
+
+        FileExt::unlock(&manifest)?;
+        result
+    }
+
+    /// Replaces the saved head, through a temporary file so it is never half written
+    fn save_head(&self, head: &ManifestHead) -> Result<()> {
+        let temporary = format!("{}.tmp", self.config.head_path);
+        std::fs::write(&temporary, format!("{}\n", head))
+            .context(format!("Failed to write manifest head: {}", temporary))?;
+        std::fs::rename(&temporary, &self.config.head_path)
+            .context(format!("Failed to write manifest head: {}", self.config.head_path))?;
+        Ok(())
+    }
+}
+
+/// Last non-empty line of a file, read from its end
+fn last_line(file: &mut File) -> Result<Option<String>> {
+    let length = file.metadata()?.len();
+    let mut chunk = TAIL_CHUNK;
+    loop {
+        let start = length.saturating_sub(chunk);
+        file.seek(SeekFrom::Start(start))?;
+        let mut tail = String::new();
+        file.read_to_string(&mut tail)?;
+
+        let trimmed = tail.trim_end();
+        match trimmed.rfind('\n') {
+            Some(newline) => return Ok(Some(trimmed[newline + 1..].to_string())),
+            None if start == 0 => return Ok(Some(trimmed.to_string()).filter(|line| !line.is_empty())),
+            None => chunk *= 2,
+        }
+    }
+}
+
+/// Footage files in the directories, as `<directory>/<name>` paths
+fn footage_files(directories: &[PathBuf]) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for directory in directories {
+        if !directory.exists() {
+            continue;
+        }
+        let entries = std::fs::read_dir(directory)
+            .context(format!("Failed to read footage directory: {}", directory.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+            // Hidden files are clips and segments still being recorded
+            if !name.starts_with('.') && FOOTAGE_EXTENSIONS.contains(&extension.as_str()) && entry.metadata()?.is_file() {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"manifest test secret";
+
+    /// Manifest over a fresh directory with `count` stored clips
+    fn stored(name: &str, count: usize) -> (Manifest, PathBuf) {
+        let directory = std::env::temp_dir().join(format!("manifest_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let config = ManifestConfig {
+            enabled: true,
+            path: directory.join("manifest.jsonl").to_string_lossy().to_string(),
+            head_path: directory.join("manifest.head").to_string_lossy().to_string(),
+        };
+        let manifest = Manifest::new(config, SECRET);
+        for i in 0..count {
+            let clip = directory.join(format!("clip_{}.avi", i));
+            std::fs::write(&clip, format!("clip {}", i)).unwrap();
+            manifest.record_stored(&clip.to_string_lossy()).unwrap();
+        }
+        (manifest, directory)
+    }
+
+    /// Drops the last `count` lines of the manifest
+    fn cut_tail(manifest: &Manifest, count: usize) {
+        let lines = manifest.lines().unwrap();
+        let kept = &lines[..lines.len() - count];
+        std::fs::write(&manifest.config.path, kept.iter().map(|line| format!("{}\n", line)).collect::<String>()).unwrap();
+    }
+
+    #[test]
+    fn intact_chain_verifies_up_to_its_head() {
+        let (manifest, directory) = stored("intact", 3);
+        let report = manifest.verify(std::slice::from_ref(&directory), None).unwrap();
+        assert!(report.is_intact(), "{:?}", report.problems);
+        assert_eq!(report.head, manifest.saved_head().unwrap());
+        assert_eq!(report.head.unwrap().sequence, 2);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn cutting_entries_off_the_end_is_detected() {
+        let (manifest, directory) = stored("truncated", 3);
+        let exported = manifest.saved_head().unwrap().unwrap();
+        cut_tail(&manifest, 1);
+        std::fs::remove_file(directory.join("clip_2.avi")).unwrap();
+
+        let report = manifest.verify(std::slice::from_ref(&directory), None).unwrap();
+        assert_eq!(report.problems, vec![ManifestProblem::Truncated { head: 2, last: Some(1) }]);
+
+        // Also caught by an exported head once the head file is rewritten to match
+        manifest.save_head(&report.head.clone().unwrap()).unwrap();
+        let report = manifest.verify(std::slice::from_ref(&directory), Some(&exported)).unwrap();
+        assert_eq!(report.problems, vec![ManifestProblem::Truncated { head: 2, last: Some(1) }]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn older_exported_head_must_still_be_in_the_chain() {
+        let (manifest, directory) = stored("exported", 2);
+        let mut exported = manifest.saved_head().unwrap().unwrap();
+        let clip = directory.join("clip_late.avi");
+        std::fs::write(&clip, "late").unwrap();
+        manifest.record_stored(&clip.to_string_lossy()).unwrap();
+        assert!(manifest.verify(std::slice::from_ref(&directory), Some(&exported)).unwrap().is_intact());
+
+        exported.entry_hash = GENESIS_HASH.to_string();
+        let report = manifest.verify(std::slice::from_ref(&directory), Some(&exported)).unwrap();
+        assert_eq!(report.problems, vec![ManifestProblem::HeadMismatch { sequence: 1 }]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn chain_rebuilt_without_the_secret_is_detected() {
+        let (manifest, directory) = stored("rebuilt", 2);
+        let forger = Manifest::new(manifest.config.clone(), b"guessed secret");
+        std::fs::remove_file(&manifest.config.path).unwrap();
+        for i in 0..2 {
+            forger.record_stored(&directory.join(format!("clip_{}.avi", i)).to_string_lossy()).unwrap();
+        }
+
+        let report = manifest.verify(std::slice::from_ref(&directory), None).unwrap();
+        assert_eq!(report.problems, vec![
+            ManifestProblem::EntryModified { sequence: 0 },
+            ManifestProblem::EntryModified { sequence: 1 },
+        ]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn pruned_files_keep_their_stored_hash() {
+        let (manifest, directory) = stored("pruned", 3);
+        let mut files = manifest.stored_files().unwrap();
+        for i in 0..2 {
+            let clip = directory.join(format!("clip_{}.avi", i)).to_string_lossy().to_string();
+            std::fs::remove_file(&clip).unwrap();
+            let stored = files.remove(&clip).unwrap();
+            let pruned = manifest.record_pruned(&clip, Some(&stored)).unwrap();
+            assert_eq!((pruned.sha256, pruned.size), (stored.sha256, stored.size));
+        }
+
+        assert_eq!(manifest.stored_files().unwrap().len(), 1);
+        assert!(manifest.verify(std::slice::from_ref(&directory), None).unwrap().is_intact());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn head_parses_from_its_display() {
+        let head = ManifestHead { sequence: 41, entry_hash: "ab12".to_string() };
+        assert_eq!(head.to_string().parse::<ManifestHead>().unwrap(), head);
+        assert!("41".parse::<ManifestHead>().is_err());
+        assert!("x:ab12".parse::<ManifestHead>().is_err());
+    }
+}
@@ -1,4 +1,23 @@
 // Camera module declarations
+pub mod background;
 pub mod capture;
+pub mod catalogue;
+pub mod classifier;
+pub mod config;
+pub mod decoder;
+pub mod event;
+pub mod localizer;
 pub mod logging;
-pub mod storage;
\ No newline at end of file
+pub mod manifest;
+pub mod policy;
+pub mod profile;
+pub mod recording;
+pub mod retention;
+pub mod scene;
+pub mod simulator;
+pub mod source;
+pub mod storage;
+pub mod tamper;
+pub mod verdict;
+pub mod verifier;
+pub mod zones;
\ No newline at end of file
@@ -0,0 +1,282 @@
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+use crate::controller::camera::verdict::{Verdict, VerdictStatus};
+use crate::controller::camera::verifier::VERIFICATION_HOLD_DURATION;
+
+/// Verification state of the footage over successive LED frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationState {
+    Pending,   // Nothing verified or failed yet
+    Verified,  // The LED code is read as expected
+    Suspect,   // Recent windows failed, but not enough of them to raise tamper
+    Tampered,  // Enough consecutive windows failed to raise tamper
+    Recovered, // Verifying again after a tamper, not yet trusted as Verified
+}
+
+impl VerificationState {
+    /// Lower-case name, used in logs and file names
+    pub fn label(&self) -> &'static str {
+        match self {
+            VerificationState::Pending => "pending",
+            VerificationState::Verified => "verified",
+            VerificationState::Suspect => "suspect",
+            VerificationState::Tampered => "tampered",
+            VerificationState::Recovered => "recovered",
+        }
+    }
+}
+
+impl fmt::Display for VerificationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            VerificationState::Pending => "PENDING",
+            VerificationState::Verified => "VERIFIED",
+            VerificationState::Suspect => "SUSPECT",
+            VerificationState::Tampered => "TAMPERED",
+            VerificationState::Recovered => "RECOVERED",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// When failed LED frames turn into a tamper alarm, and back
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct VerificationPolicy {
+    pub hold_ms: u64,              // Grace period past the frame period for the next LED frame before a window counts as missed
+    pub suspect_grace_ms: u64,     // Minimum time in Suspect before tamper is raised
+    pub failed_windows: usize,     // Consecutive failed windows that raise tamper
+    pub recovery_windows: usize,   // Consecutive verified windows after a tamper before Verified again
+    pub indeterminate_fails: bool, // Count indeterminate and missed windows as failed
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        Self {
+            hold_ms: VERIFICATION_HOLD_DURATION.as_millis() as u64,
+            suspect_grace_ms: 0,
+            failed_windows: 3,
+            recovery_windows: 2,
+            indeterminate_fails: true,
+        }
+    }
+}
+
+impl VerificationPolicy {
+    /// Grace period for the next LED frame
+    pub fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold_ms)
+    }
+
+    /// Minimum time in Suspect before tamper is raised
+    pub fn suspect_grace(&self) -> Duration {
+        Duration::from_millis(self.suspect_grace_ms)
+    }
+}
+
+/// One change of verification state, as passed to transition hooks
+#[derive(Debug, Clone, Copy)]
+pub struct StateTransition {
+    pub from: VerificationState,
+    pub to: VerificationState,
+    pub timestamp: Duration,   // Source timestamp of the window that caused the change
+    pub verdict: Verdict,      // Verdict of that window
+    pub failed_windows: usize, // Consecutive failed windows at the time
+}
+
+/// Callback fired on every state transition
+pub type TransitionHook = Box<dyn FnMut(&StateTransition) + Send>;
+
+/// Tracks the verification state from the verdict of every LED frame window
+///
+/// A failed window moves Verified to Suspect; once `failed_windows` windows
+/// in a row have failed and the suspect grace period is over, the state
+/// becomes Tampered. The first verified window after a tamper gives
+/// Recovered, which becomes Verified after `recovery_windows` verified
+/// windows in a row. A window without any decoded LED frame counts as an
+/// indeterminate one, including the windows before the first LED frame is
+/// ever decoded, so LEDs that are covered from the start still raise tamper.
+pub struct VerificationStateMachine {
+    policy: VerificationPolicy,
+    state: VerificationState,
+    failed_windows: usize,           // Consecutive failed windows
+    verified_windows: usize,         // Consecutive verified windows
+    suspect_since: Option<Duration>, // Source timestamp Suspect was entered
+    last_window: Option<Duration>,   // Source timestamp of the last window, decoded or missed
+    first_check: Option<Duration>,   // Source timestamp of the first frame checked for a missed window
+    hooks: Vec<TransitionHook>,
+}
+
+impl VerificationStateMachine {
+    /// Creates a state machine in the Pending state
+    pub fn new(policy: VerificationPolicy) -> Self {
+        Self {
+            policy,
+            state: VerificationState::Pending,
+            failed_windows: 0,
+            verified_windows: 0,
+            suspect_since: None,
+            last_window: None,
+            first_check: None,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Registers a callback fired on every state transition
+    pub fn on_transition(&mut self, hook: impl FnMut(&StateTransition) + Send + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Replaces the policy, keeping the current state and hooks
+    pub fn set_policy(&mut self, policy: VerificationPolicy) {
+        self.policy = policy;
+    }
+
+    /// Policy in use
+    pub fn policy(&self) -> &VerificationPolicy {
+        &self.policy
+    }
+
+    /// Current verification state
+    pub fn state(&self) -> VerificationState {
+        self.state
+    }
+
+    /// Number of consecutive failed windows
+    pub fn failed_windows(&self) -> usize {
+        self.failed_windows
+    }
+
+    /// Records the verdict of one decoded LED frame
+    ///
+    /// # Arguments
+    /// * `timestamp` - Source timestamp at which the frame was decoded
+    /// * `verdict` - Verdict on the frame
+    ///
+    /// # Returns
+    /// * `VerificationState` - State after the window
+    pub fn record_window(&mut self, timestamp: Duration, verdict: Verdict) -> VerificationState {
+        self.last_window = Some(timestamp);
+
+        let failed = match verdict.status {
+            VerdictStatus::Verified => false,
+            VerdictStatus::Unverified => true,
+            VerdictStatus::Indeterminate => self.policy.indeterminate_fails,
+        };
+        if verdict.status == VerdictStatus::Verified {
+            self.failed_windows = 0;
+            self.verified_windows += 1;
+        } else if failed {
+            self.failed_windows += 1;
+            self.verified_windows = 0;
+        }
+
+        let next = self.next_state(timestamp, verdict.status);
+        self.transition(next, timestamp, verdict);
+        self.state
+    }
+
+    /// Records a missed window if no LED frame arrived in time
+    ///
+    /// Until the first window, the clock runs from the first frame checked;
+    /// the first LED frame is given an extra frame period, since decoding can
+    /// only begin at the next preamble.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Source timestamp of the current camera frame
+    /// * `frame_period` - Duration of one LED frame
+    ///
+    /// # Returns
+    /// * `bool` - Whether a window was missed
+    pub fn check_missed(&mut self, timestamp: Duration, frame_period: Duration) -> bool {
+        let since = match self.last_window {
+            Some(last) => timestamp.saturating_sub(last),
+            None => timestamp.saturating_sub(*self.first_check.get_or_insert(timestamp)).saturating_sub(frame_period),
+        };
+        let missed = since >= frame_period + self.policy.hold();
+        if missed {
+            self.record_window(timestamp, Verdict::indeterminate());
+        }
+        missed
+    }
+
+    fn next_state(&self, timestamp: Duration, status: VerdictStatus) -> VerificationState {
+        if status == VerdictStatus::Verified {
+            return match self.state {
+                VerificationState::Tampered => VerificationState::Recovered,
+                VerificationState::Recovered if self.verified_windows < self.policy.recovery_windows => VerificationState::Recovered,
+                _ => VerificationState::Verified,
+            };
+        }
+        if self.failed_windows == 0 {
+            // An indeterminate window that does not count as failed changes nothing
+            return self.state;
+        }
+
+        let suspect_for = timestamp.saturating_sub(self.suspect_since.unwrap_or(timestamp));
+        let raised = self.failed_windows >= self.policy.failed_windows.max(1) && suspect_for >= self.policy.suspect_grace();
+        if raised || self.state == VerificationState::Tampered {
+            VerificationState::Tampered
+        } else {
+            VerificationState::Suspect
+        }
+    }
+
+    fn transition(&mut self, to: VerificationState, timestamp: Duration, verdict: Verdict) {
+        let from = self.state;
+        if to == from {
+            return;
+        }
+        match to {
+            VerificationState::Suspect => self.suspect_since = Some(timestamp),
+            VerificationState::Verified | VerificationState::Recovered => self.suspect_since = None,
+            VerificationState::Pending | VerificationState::Tampered => {}
+        }
+        self.state = to;
+
+        let transition = StateTransition { from, to, timestamp, verdict, failed_windows: self.failed_windows };
+        for hook in self.hooks.iter_mut() {
+            hook(&transition);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_PERIOD: Duration = Duration::from_secs(19);
+
+    /// Checks for missed windows every 100ms from `start` for `span`
+    fn run_without_frames(machine: &mut VerificationStateMachine, start: Duration, span: Duration) {
+        let mut timestamp = start;
+        while timestamp < start + span {
+            machine.check_missed(timestamp, FRAME_PERIOD);
+            timestamp += Duration::from_millis(100);
+        }
+    }
+
+    #[test]
+    fn leds_never_decoded_raise_tamper() {
+        let mut machine = VerificationStateMachine::new(VerificationPolicy::default());
+        let policy = machine.policy().clone();
+        let window = FRAME_PERIOD + policy.hold();
+
+        // The first window is given an extra frame period
+        run_without_frames(&mut machine, Duration::from_secs(5), FRAME_PERIOD + window - Duration::from_millis(100));
+        assert_eq!(machine.state(), VerificationState::Pending);
+
+        run_without_frames(&mut machine, Duration::from_secs(5) + FRAME_PERIOD + window, window * policy.failed_windows as u32);
+        assert_eq!(machine.state(), VerificationState::Tampered);
+    }
+
+    #[test]
+    fn first_decoded_frame_in_time_is_not_missed() {
+        let mut machine = VerificationStateMachine::new(VerificationPolicy::default());
+        run_without_frames(&mut machine, Duration::ZERO, FRAME_PERIOD * 2);
+        machine.record_window(FRAME_PERIOD * 2, Verdict { status: VerdictStatus::Verified, confidence: 1.0 });
+        assert_eq!(machine.state(), VerificationState::Verified);
+        assert_eq!(machine.failed_windows(), 0);
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::path::Path;
+use crate::controller::camera::config::RectConfig;
+use crate::controller::camera::verifier::{LedRange, LedRois, Thresholds};
+
+/// Saved result of an LED calibration
+///
+/// Reloading the profile on startup skips localisation and calibration as long
+/// as the camera still sees the LEDs the way it did when the profile was made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    pub created_at: String,     // Local time the calibration was done
+    pub frame_width: i32,       // Frame size the ROIs refer to
+    pub frame_height: i32,
+    pub rois: Vec<RectConfig>,  // LED regions, in channel order
+    pub leds: Vec<LedRange>,    // Calibrated brightness statistics per LED
+}
+
+impl CalibrationProfile {
+    /// Captures a calibration as a profile
+    ///
+    /// # Arguments
+    /// * `frame_width` - Width of the frames the calibration was done on
+    /// * `frame_height` - Height of the frames the calibration was done on
+    /// * `rois` - LED regions
+    /// * `thresholds` - Calibrated brightness ranges
+    pub fn new(frame_width: i32, frame_height: i32, rois: &LedRois, thresholds: &Thresholds) -> Self {
+        Self {
+            created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            frame_width,
+            frame_height,
+            rois: rois.rects.iter().map(|&rect| rect.into()).collect(),
+            leds: thresholds.leds.clone(),
+        }
+    }
+
+    /// Loads a profile
+    ///
+    /// # Returns
+    /// * `Result<Option<CalibrationProfile>>` - The profile, None if there is no
+    ///   profile yet, or an error if it cannot be read
+    pub fn load(path: &str) -> Result<Option<Self>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path)
+            .context(format!("Failed to read calibration profile: {}", path))?;
+        let profile = toml::from_str(&text)
+            .context(format!("Failed to parse calibration profile: {}", path))?;
+        Ok(Some(profile))
+    }
+
+    /// Saves the profile, replacing any previous one in a single step
+    pub fn save(&self, path: &str) -> Result<()> {
+        let text = toml::to_string_pretty(self).context("Failed to serialise calibration profile")?;
+        let temp_path = format!("{}.tmp", path);
+        std::fs::write(&temp_path, text)
+            .context(format!("Failed to write calibration profile: {}", temp_path))?;
+        std::fs::rename(&temp_path, path)
+            .context(format!("Failed to replace calibration profile: {}", path))
+    }
+
+    /// LED regions of the profile
+    pub fn led_rois(&self) -> LedRois {
+        LedRois {
+            rects: self.rois.iter().map(|&rect| rect.into()).collect(),
+        }
+    }
+
+    /// Brightness ranges of the profile
+    pub fn thresholds(&self) -> Thresholds {
+        Thresholds { leds: self.leds.clone() }
+    }
+
+    /// Whether the profile was made for this frame size and number of LEDs
+    pub fn matches(&self, frame_width: i32, frame_height: i32, led_count: usize) -> bool {
+        self.frame_width == frame_width
+            && self.frame_height == frame_height
+            && self.rois.len() == led_count
+            && self.leds.len() == led_count
+    }
+
+    /// Whether measured LED brightnesses lie within the calibrated ranges
+    ///
+    /// # Arguments
+    /// * `brightnesses` - Brightness of every LED in one frame
+    /// * `tolerance` - Allowed excursion outside a range, relative to its contrast
+    pub fn fits(&self, brightnesses: &[f64], tolerance: f64) -> bool {
+        brightnesses.len() == self.leds.len()
+            && brightnesses.iter().zip(&self.leds).all(|(&brightness, range)| {
+                let slack = range.contrast() * tolerance + range.noise * 3.0;
+                brightness >= range.off - slack && brightness <= range.on + slack
+            })
+    }
+}
@@ -0,0 +1,287 @@
+use opencv::{
+    core::{Mat, Size},
+    prelude::*,
+    videoio::VideoWriter,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::controller::camera::catalogue::{Catalogue, EventRecord, FileKind};
+use crate::controller::camera::logging;
+use crate::controller::camera::manifest::Manifest;
+use crate::controller::camera::retention;
+use crate::controller::camera::storage::{move_recording, FILE_TIME_FORMAT};
+use crate::controller::camera::tamper::TamperEvent;
+
+// File the event markers of all segments are appended to
+pub const MARKERS_FILE: &str = "markers.txt";
+
+// Prefix and suffix of segments still being recorded
+const PARTIAL_PREFIX: &str = ".segment_";
+const PARTIAL_SUFFIX: &str = ".partial.avi";
+
+/// Settings of the continuous recording
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    pub directory: String,    // Where the segments and the marker file are written
+    pub segment_minutes: u64, // Length of each segment
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "recordings".to_string(),
+            segment_minutes: 5,
+        }
+    }
+}
+
+impl RecordingConfig {
+    /// Length of each segment
+    pub fn segment_length(&self) -> Duration {
+        Duration::from_secs(self.segment_minutes.max(1) * 60)
+    }
+}
+
+/// A point of interest inside a segment
+#[derive(Debug, Clone)]
+pub struct EventMarker {
+    pub segment: String,  // File name of the segment
+    pub offset: Duration, // Position of the event from the start of the segment
+    pub event: String,    // What happened
+}
+
+impl EventMarker {
+    /// Line of the marker file: segment, offset in seconds and event, tab separated
+    pub fn to_line(&self) -> String {
+        format!("{}\t{:.3}\t{}", self.segment, self.offset.as_secs_f64(), self.event)
+    }
+}
+
+/// Segment currently being written
+struct Segment {
+    writer: VideoWriter,
+    name: String,               // File name once finished, segment_<start time>.avi
+    partial: PathBuf,           // Hidden file the frames are written to until then
+    started: Duration,          // Source timestamp of its first frame
+    opened_at: DateTime<Local>, // Wall-clock time of its first frame
+    size: Size,
+    frames: usize,
+    tamper: Vec<String>,        // Tampering detected while it was recorded
+}
+
+/// Records every frame into fixed-length segments
+///
+/// Segments are named `segment_<YYYYmmdd_HHMMSS_mmm>.avi` after the wall-clock
+/// time of their first frame, and a new one starts once the current one spans
+/// `segment_minutes` of source time (or the frame size changes). Events are
+/// written to `markers.txt` with the segment and the offset inside it, so the
+/// footage around an event, and between events, can be found again. Like
+/// event clips, a segment is written under a hidden partial name and only
+/// renamed once it is finished.
+pub struct ContinuousRecorder {
+    config: RecordingConfig,
+    segment: Option<Segment>,
+    catalogue: Option<Catalogue>, // Where finished segments are recorded
+    manifest: Option<Manifest>,   // Hash chain finished segments are appended to
+    recovered: Vec<String>,       // Interrupted segments recovered on start
+}
+
+impl ContinuousRecorder {
+    /// Creates a recorder, creating its directory if needed and recovering
+    /// the segments left half-written by a crash
+    pub fn new(config: RecordingConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.directory)
+            .context(format!("Failed to create recording directory: {}", config.directory))?;
+        let mut recorder = Self {
+            config,
+            segment: None,
+            catalogue: None,
+            manifest: None,
+            recovered: Vec::new(),
+        };
+        recorder.recover_partial_segments()?;
+        Ok(recorder)
+    }
+
+    /// Keeps the segments left half-written by a crash as
+    /// `segment_<start time>_interrupted.avi`, which retention protects
+    fn recover_partial_segments(&mut self) -> Result<()> {
+        let entries = std::fs::read_dir(&self.config.directory)
+            .context(format!("Failed to read recording directory: {}", self.config.directory))?;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(timestamp) = name.strip_prefix(PARTIAL_PREFIX).and_then(|rest| rest.strip_suffix(PARTIAL_SUFFIX)) else {
+                continue;
+            };
+            let recovered = Path::new(&self.config.directory).join(format!("segment_{}_interrupted.avi", timestamp));
+            move_recording(&path, &recovered).context("Failed to recover partial segment")?;
+            logging::log_event(&format!("Recovered interrupted segment: {}", recovered.display()))?;
+            self.recovered.push(recovered.to_string_lossy().to_string());
+        }
+        Ok(())
+    }
+
+    /// Records every finished segment in a catalogue
+    pub fn with_catalogue(mut self, catalogue: Catalogue) -> Self {
+        self.catalogue = Some(catalogue);
+        self
+    }
+
+    /// Appends every finished segment to a hash-chained manifest
+    ///
+    /// Segments recovered after a crash are appended straight away.
+    pub fn with_manifest(mut self, manifest: Manifest) -> Result<Self> {
+        for path in std::mem::take(&mut self.recovered) {
+            manifest.record_stored(&path)?;
+        }
+        self.manifest = Some(manifest);
+        Ok(self)
+    }
+
+    /// Appends a frame, starting a new segment when the current one is full
+    ///
+    /// # Arguments
+    /// * `frame` - The frame to record
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    /// * `fps` - Frames per second of the segments
+    pub fn write(&mut self, frame: &Mat, timestamp: Duration, fps: f64) -> Result<()> {
+        let size = frame.size()?;
+        let full = self.segment.as_ref().is_none_or(|segment| {
+            segment.size != size
+                || timestamp < segment.started
+                || timestamp - segment.started >= self.config.segment_length()
+        });
+        if full {
+            self.start_segment(size, timestamp, fps)?;
+        }
+        if let Some(segment) = self.segment.as_mut() {
+            segment.writer.write(frame).context("Failed to write segment frame")?;
+            segment.frames += 1;
+        }
+        Ok(())
+    }
+
+    /// Marks an event at a position of the current segment
+    ///
+    /// # Arguments
+    /// * `timestamp` - Source timestamp of the event
+    /// * `event` - What happened
+    ///
+    /// # Returns
+    /// * `Result<Option<EventMarker>>` - The marker, None if no segment is being written
+    pub fn mark(&mut self, timestamp: Duration, event: &str) -> Result<Option<EventMarker>> {
+        let Some(segment) = self.segment.as_ref() else {
+            return Ok(None);
+        };
+        let marker = EventMarker {
+            segment: segment.name.clone(),
+            offset: timestamp.saturating_sub(segment.started),
+            event: event.to_string(),
+        };
+
+        let path = Path::new(&self.config.directory).join(MARKERS_FILE);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open marker file: {}", path.display()))?;
+        writeln!(file, "{}", marker.to_line())?;
+        Ok(Some(marker))
+    }
+
+    /// Marks tampering in the current segment and flags the segment as
+    /// evidence, so retention keeps it
+    ///
+    /// # Arguments
+    /// * `timestamp` - Source timestamp of the frame the tampering was detected in
+    /// * `events` - Tampering detected in that frame
+    pub fn record_tamper(&mut self, timestamp: Duration, events: &[TamperEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        for event in events {
+            self.mark(timestamp, &format!("Tamper detected: {}", event))?;
+        }
+        if let Some(segment) = self.segment.as_mut() {
+            segment.tamper.extend(events.iter().map(|event| event.to_string()));
+            retention::flag_evidence(&Path::new(&self.config.directory).join(&segment.name))?;
+        }
+        Ok(())
+    }
+
+    /// File name of the segment being written
+    pub fn current_segment(&self) -> Option<&str> {
+        self.segment.as_ref().map(|segment| segment.name.as_str())
+    }
+
+    /// Closes the current segment, renaming it to its final name and
+    /// recording it in the manifest and the catalogue
+    pub fn finish(&mut self) -> Result<()> {
+        let Some(mut segment) = self.segment.take() else {
+            return Ok(());
+        };
+        segment.writer.release()?;
+
+        let path = Path::new(&self.config.directory).join(&segment.name);
+        move_recording(&segment.partial, &path).context("Failed to finish segment")?;
+        let path = path.to_string_lossy().to_string();
+        if let Some(manifest) = self.manifest.as_ref() {
+            manifest.record_stored(&path)?;
+        }
+        if let Some(catalogue) = self.catalogue.as_ref() {
+            let mut record = EventRecord::new(
+                &path,
+                FileKind::Segment,
+                "segment",
+                catalogue.camera_id(),
+                segment.opened_at,
+                Local::now(),
+            )?;
+            record.frames = segment.frames;
+            record.tamper = segment.tamper;
+            catalogue.record(&record)?;
+        }
+        Ok(())
+    }
+
+    fn start_segment(&mut self, size: Size, timestamp: Duration, fps: f64) -> Result<()> {
+        self.finish()?;
+
+        let stamp = Local::now().format(FILE_TIME_FORMAT).to_string();
+        let name = format!("segment_{}.avi", stamp);
+        let partial = Path::new(&self.config.directory).join(format!("{}{}{}", PARTIAL_PREFIX, stamp, PARTIAL_SUFFIX));
+        let writer = VideoWriter::new(
+            &partial.to_string_lossy(),
+            VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+            fps,
+            size,
+            true,
+        ).context("Failed to create segment writer")?;
+        if !writer.is_opened()? {
+            return Err(anyhow::anyhow!("Failed to open segment writer: {}", partial.display()));
+        }
+
+        self.segment = Some(Segment {
+            writer,
+            name,
+            partial,
+            started: timestamp,
+            opened_at: Local::now(),
+            size,
+            frames: 0,
+            tamper: Vec::new(),
+        });
+        Ok(())
+    }
+}
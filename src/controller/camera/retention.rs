@@ -0,0 +1,393 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use crate::controller::camera::catalogue;
+use crate::controller::camera::logging;
+use crate::controller::camera::manifest::{Manifest, ManifestEntry};
+
+// Marker file next to a clip that must never be deleted: <clip>.evidence
+pub const EVIDENCE_SUFFIX: &str = ".evidence";
+
+// Footage the janitor looks at; everything else in the directories is left alone
+pub const FOOTAGE_EXTENSIONS: [&str; 2] = ["avi", "jpg"];
+
+// Files modified more recently than this may still be being written
+const MIN_AGE: Duration = Duration::from_secs(60);
+
+const MB: u64 = 1024 * 1024;
+
+/// Settings of the footage retention
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    pub max_age_days: u64,             // Footage older than this is deleted, 0 to keep it forever
+    pub max_total_mb: u64,             // Size the footage may take up in total, 0 for no limit
+    pub min_free_mb: u64,              // Free disk space to keep, 0 for no floor
+    pub check_interval_s: u64,         // Time between two sweeps of the janitor
+    pub protected_labels: Vec<String>, // Footage whose file name contains one of these is never deleted
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: 30,
+            max_total_mb: 0,
+            min_free_mb: 1024,
+            check_interval_s: 300,
+            protected_labels: ["unverified", "tamper", "evidence", "interrupted"].map(String::from).to_vec(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    fn max_age(&self) -> Option<Duration> {
+        (self.max_age_days > 0).then(|| Duration::from_secs(self.max_age_days * 24 * 60 * 60))
+    }
+}
+
+/// Flags a clip as evidence so retention never deletes it
+pub fn flag_evidence(path: &Path) -> Result<()> {
+    let flag = evidence_flag(path);
+    std::fs::write(&flag, b"").context(format!("Failed to flag evidence: {}", path.display()))?;
+    Ok(())
+}
+
+/// Whether a clip has been flagged as evidence
+pub fn is_flagged(path: &Path) -> bool {
+    evidence_flag(path).exists()
+}
+
+fn evidence_flag(path: &Path) -> PathBuf {
+    let mut flag = path.as_os_str().to_owned();
+    flag.push(EVIDENCE_SUFFIX);
+    PathBuf::from(flag)
+}
+
+/// A footage file found by a sweep
+struct Footage {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    protected: bool,
+}
+
+/// Outcome of one retention sweep
+#[derive(Debug, Default)]
+pub struct SweepReport {
+    pub deleted: Vec<PathBuf>, // Files deleted, oldest first
+    pub freed: u64,            // Bytes freed
+    pub total: u64,            // Bytes of footage left
+}
+
+/// Deletes old footage by age, total size and free disk space
+///
+/// The oldest footage goes first. Footage flagged as evidence, or whose name
+/// carries a protected label such as `unverified`, is never deleted but still
+/// counts towards the total size.
+pub struct Retention {
+    config: RetentionConfig,
+    directories: Vec<PathBuf>, // Where footage is stored, e.g. the event clips and the segments
+    manifest: Option<Manifest>, // Hash chain deletions are recorded in as pruned
+}
+
+impl Retention {
+    /// Creates a retention policy over the given footage directories
+    pub fn new(config: RetentionConfig, directories: Vec<PathBuf>) -> Self {
+        Self { config, directories, manifest: None }
+    }
+
+    /// Records every deletion as pruned in a hash-chained manifest
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Whether a footage file must never be deleted
+    pub fn is_protected(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        is_flagged(path) || self.config.protected_labels.iter().any(|label| name.contains(&label.to_lowercase()))
+    }
+
+    /// Deletes footage until the age, size and free space limits are met
+    ///
+    /// # Returns
+    /// * `Result<SweepReport>` - What was deleted
+    pub fn sweep(&self) -> Result<SweepReport> {
+        let mut footage = self.footage()?;
+        footage.sort_by_key(|file| file.modified);
+
+        let now = SystemTime::now();
+        let max_total = self.config.max_total_mb * MB;
+        let min_free = self.config.min_free_mb * MB;
+        let mut report = SweepReport { total: footage.iter().map(|file| file.size).sum(), ..Default::default() };
+        let mut free: HashMap<PathBuf, u64> = HashMap::new();
+        // Stored manifest entries, read once on the first deletion
+        let mut stored: Option<HashMap<String, ManifestEntry>> = None;
+
+        for file in footage.iter().filter(|file| !file.protected) {
+            let age = now.duration_since(file.modified).unwrap_or(Duration::ZERO);
+            if age < MIN_AGE {
+                break;
+            }
+            let directory = file.path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let available = match free.get(&directory) {
+                Some(&available) => available,
+                None => fs2::available_space(&directory).unwrap_or(u64::MAX),
+            };
+
+            let too_old = self.config.max_age().is_some_and(|max_age| age > max_age);
+            let over_quota = max_total > 0 && report.total > max_total;
+            let low_space = available < min_free;
+            if !(too_old || over_quota || low_space) {
+                // Newer files are neither too old nor needed to free space
+                break;
+            }
+
+            std::fs::remove_file(&file.path).context(format!("Failed to delete footage: {}", file.path.display()))?;
+            let sidecar = catalogue::sidecar_path(&file.path);
+            if sidecar.exists() {
+                std::fs::remove_file(&sidecar).context(format!("Failed to delete sidecar: {}", sidecar.display()))?;
+            }
+            let reason = if too_old { "age" } else if over_quota { "total size" } else { "free space" };
+            logging::log_event(&format!("Retention deleted {} ({})", file.path.display(), reason))?;
+            if let Some(manifest) = self.manifest.as_ref() {
+                let stored = match stored.as_mut() {
+                    Some(stored) => stored,
+                    None => stored.insert(manifest.stored_files()?),
+                };
+                let file = file.path.to_string_lossy();
+                manifest.record_pruned(&file, stored.remove(file.as_ref()).as_ref())?;
+            }
+
+            free.insert(directory, available.saturating_add(file.size));
+            report.total -= file.size;
+            report.freed += file.size;
+            report.deleted.push(file.path.clone());
+        }
+
+        if max_total > 0 && report.total > max_total {
+            logging::log_event("Retention: footage over quota, only protected or recent footage is left")?;
+        }
+        Ok(report)
+    }
+
+    /// Footage files in the directories
+    fn footage(&self) -> Result<Vec<Footage>> {
+        let mut footage = Vec::new();
+        for directory in &self.directories {
+            if !directory.exists() {
+                continue;
+            }
+            let entries = std::fs::read_dir(directory)
+                .context(format!("Failed to read footage directory: {}", directory.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+                // Hidden files are clips and segments still being recorded
+                if name.starts_with('.') || !FOOTAGE_EXTENSIONS.contains(&extension.as_str()) {
+                    continue;
+                }
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                footage.push(Footage {
+                    protected: self.is_protected(&path),
+                    path,
+                    size: metadata.len(),
+                    modified: metadata.modified()?,
+                });
+            }
+        }
+        Ok(footage)
+    }
+}
+
+/// Runs retention sweeps on a background thread
+///
+/// The janitor sweeps once on start and then every `check_interval_s`, until
+/// it is stopped or dropped.
+pub struct RetentionJanitor {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RetentionJanitor {
+    /// Starts the janitor
+    ///
+    /// # Arguments
+    /// * `retention` - Policy applied by every sweep
+    pub fn spawn(retention: Retention) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let interval = Duration::from_secs(retention.config.check_interval_s.max(1));
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                // Nothing to report the outcome to on this thread but the security log
+                let outcome = match retention.sweep() {
+                    Ok(report) if !report.deleted.is_empty() => {
+                        Some(format!("Retention deleted {} files, {} MB freed", report.deleted.len(), report.freed / MB))
+                    }
+                    Ok(_) => None,
+                    Err(e) => Some(format!("Retention sweep failed: {}", e)),
+                };
+                if let Some(outcome) = outcome {
+                    let _ = logging::log_event(&outcome);
+                }
+
+                // Sleep in short steps so stopping does not wait for a whole interval
+                let mut slept = Duration::ZERO;
+                while slept < interval && !thread_stop.load(Ordering::Relaxed) {
+                    let step = Duration::from_secs(1).min(interval - slept);
+                    thread::sleep(step);
+                    slept += step;
+                }
+            }
+        });
+
+        Self { stop, handle: Some(handle) }
+    }
+
+    /// Stops the janitor and waits for the sweep in progress
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for RetentionJanitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::camera::manifest::ManifestConfig;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// An empty footage directory for one test
+    fn footage_dir(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("retention_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// Writes a file of `size` bytes last modified `age` ago
+    fn write_file(directory: &Path, name: &str, size: usize, age: Duration) -> PathBuf {
+        let path = directory.join(name);
+        std::fs::write(&path, vec![0u8; size]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+        path
+    }
+
+    /// Limits by age only, one day
+    fn by_age() -> RetentionConfig {
+        RetentionConfig { max_age_days: 1, max_total_mb: 0, min_free_mb: 0, ..RetentionConfig::default() }
+    }
+
+    /// Limits by total size only
+    fn by_size(max_total_mb: u64) -> RetentionConfig {
+        RetentionConfig { max_age_days: 0, max_total_mb, min_free_mb: 0, ..RetentionConfig::default() }
+    }
+
+    #[test]
+    fn oldest_footage_is_deleted_first_until_under_quota() {
+        let directory = footage_dir("quota");
+        let newest = write_file(&directory, "c.avi", 600 * 1024, HOUR);
+        let oldest = write_file(&directory, "a.avi", 600 * 1024, 3 * HOUR);
+        let middle = write_file(&directory, "b.jpg", 600 * 1024, 2 * HOUR);
+
+        let report = Retention::new(by_size(1), vec![directory.clone()]).sweep().unwrap();
+        assert_eq!(report.deleted, vec![oldest, middle]);
+        assert_eq!(report.freed, 2 * 600 * 1024);
+        assert_eq!(report.total, 600 * 1024);
+        assert!(newest.exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn footage_still_being_written_is_never_deleted() {
+        let directory = footage_dir("recent");
+        write_file(&directory, "a.avi", 600 * 1024, Duration::from_secs(10));
+        write_file(&directory, "b.avi", 600 * 1024, Duration::from_secs(5));
+
+        let report = Retention::new(by_size(1), vec![directory.clone()]).sweep().unwrap();
+        assert!(report.deleted.is_empty());
+        assert_eq!(report.total, 2 * 600 * 1024);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn protected_flagged_hidden_and_other_files_are_kept() {
+        let directory = footage_dir("protected");
+        let plain = write_file(&directory, "video_1_motion_event_verified.avi", 10, 2 * DAY);
+        let kept = [
+            write_file(&directory, "video_2_motion_event_unverified.avi", 10, 2 * DAY),
+            write_file(&directory, "segment_3_interrupted.avi", 10, 2 * DAY),
+            write_file(&directory, "video_4_motion_event_verified.avi", 10, 2 * DAY),
+            write_file(&directory, ".recording_5.partial.avi", 10, 2 * DAY),
+            write_file(&directory, "notes.txt", 10, 2 * DAY),
+        ];
+        flag_evidence(&kept[2]).unwrap();
+
+        let report = Retention::new(by_age(), vec![directory.clone()]).sweep().unwrap();
+        assert_eq!(report.deleted, vec![plain]);
+        assert!(kept.iter().all(|path| path.exists()));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn sidecars_are_deleted_with_their_footage() {
+        let directory = footage_dir("sidecar");
+        let clip = write_file(&directory, "video_1_motion_event_verified.avi", 10, 2 * DAY);
+        let sidecar = catalogue::sidecar_path(&clip);
+        std::fs::write(&sidecar, "{}").unwrap();
+
+        Retention::new(by_age(), vec![directory.clone()]).sweep().unwrap();
+        assert!(!clip.exists());
+        assert!(!sidecar.exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn deletions_are_recorded_in_the_manifest_as_pruned() {
+        let directory = footage_dir("manifest");
+        let config = ManifestConfig {
+            enabled: true,
+            path: directory.join("manifest.jsonl").to_string_lossy().to_string(),
+            head_path: directory.join("manifest.head").to_string_lossy().to_string(),
+        };
+        let manifest = Manifest::new(config, b"retention test secret");
+        let old = write_file(&directory, "a.avi", 10, 2 * DAY);
+        let new = write_file(&directory, "b.avi", 10, HOUR);
+        for path in [&old, &new] {
+            manifest.record_stored(&path.to_string_lossy()).unwrap();
+        }
+
+        let retention = Retention::new(by_age(), vec![directory.clone()]).with_manifest(manifest.clone());
+        assert_eq!(retention.sweep().unwrap().deleted, vec![old]);
+
+        let stored: Vec<String> = manifest.stored_files().unwrap().into_keys().collect();
+        assert_eq!(stored, vec![new.to_string_lossy().to_string()]);
+        assert!(manifest.verify(std::slice::from_ref(&directory), None).unwrap().is_intact());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
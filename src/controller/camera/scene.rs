@@ -0,0 +1,323 @@
+use opencv::{
+    core::{self, DMatch, KeyPoint, Mat, Ptr, Vector},
+    features2d::{self, ORB},
+    imgcodecs,
+    imgproc,
+    prelude::*,
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use crate::controller::camera::logging;
+use crate::controller::camera::tamper::TamperEvent;
+
+// Scenes with fewer keypoints than this are too plain to tell if the camera moved
+const MIN_REFERENCE_KEYPOINTS: usize = 20;
+
+// Hamming distance (of 256 bits) up to which two ORB descriptors match
+const MAX_DESCRIPTOR_DISTANCE: f32 = 64.0;
+
+/// Settings of the covered, blurred and moved camera checks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SceneHealthConfig {
+    pub enabled: bool,
+    pub reference_path: String,   // Where the reference scene is saved, empty to not save it
+    pub check_interval_ms: u64,   // Time between two checks
+    pub persistence_ms: u64,      // How long a condition must last before it is reported
+    pub min_brightness: f64,      // Mean grey level below which the lens counts as covered
+    pub min_sharpness_ratio: f64, // Laplacian variance, relative to the reference, below which the image counts as blurred
+    pub min_match_ratio: f64,     // Fraction of reference keypoints found in place below which the camera counts as moved
+    pub max_shift: f64,           // Pixels a keypoint may move and still count as in place
+    pub keypoints: i32,           // ORB keypoints detected per image
+}
+
+impl Default for SceneHealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reference_path: "scene_reference.png".to_string(),
+            check_interval_ms: 1000,
+            persistence_ms: 3000,
+            min_brightness: 15.0,
+            min_sharpness_ratio: 0.3,
+            min_match_ratio: 0.3,
+            max_shift: 20.0,
+            keypoints: 500,
+        }
+    }
+}
+
+impl SceneHealthConfig {
+    /// Path of the reference scene, if it is saved
+    pub fn reference_path(&self) -> Option<&str> {
+        Some(self.reference_path.as_str()).filter(|path| !path.is_empty())
+    }
+}
+
+/// Brightness, sharpness and keypoints of the scene as seen at calibration
+pub struct SceneReference {
+    frame: Mat,
+    brightness: f64,
+    sharpness: f64,
+    keypoints: Vector<KeyPoint>,
+    descriptors: Mat,
+}
+
+impl SceneReference {
+    /// Captures a frame as the reference scene
+    pub fn capture(frame: &Mat, orb: &mut Ptr<ORB>) -> Result<Self> {
+        let gray = grayscale(frame)?;
+        let (keypoints, descriptors) = features(&gray, orb)?;
+        Ok(Self {
+            frame: frame.clone(),
+            brightness: core::mean(&gray, &Mat::default())?.0[0],
+            sharpness: sharpness(&gray)?,
+            keypoints,
+            descriptors,
+        })
+    }
+
+    /// Saves the reference frame as an image
+    pub fn save(&self, path: &str) -> Result<()> {
+        let saved = imgcodecs::imwrite(path, &self.frame, &Vector::new())
+            .context(format!("Failed to write scene reference: {}", path))?;
+        if !saved {
+            return Err(anyhow::anyhow!("Failed to write scene reference: {}", path));
+        }
+        Ok(())
+    }
+
+    /// Loads a saved reference frame
+    ///
+    /// # Returns
+    /// * `Result<Option<Mat>>` - The frame, None if there is no reference yet
+    pub fn load_frame(path: &str) -> Result<Option<Mat>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let frame = imgcodecs::imread(path, imgcodecs::IMREAD_COLOR)
+            .context(format!("Failed to read scene reference: {}", path))?;
+        Ok(Some(frame).filter(|frame| !frame.empty()))
+    }
+
+    /// Size of the reference frame
+    pub fn size(&self) -> Result<core::Size> {
+        Ok(self.frame.size()?)
+    }
+}
+
+/// A scene condition and how long it has been seen
+#[derive(Debug, Default)]
+struct Condition {
+    since: Option<Duration>, // Timestamp the condition was first seen
+    reported: bool,
+}
+
+impl Condition {
+    /// Updates the condition
+    ///
+    /// # Returns
+    /// * `(bool, bool)` - Whether to report it now, and whether it just cleared
+    ///   after being reported
+    fn update(&mut self, present: bool, timestamp: Duration, persistence: Duration) -> (bool, bool) {
+        if !present {
+            let cleared = self.reported;
+            *self = Self::default();
+            return (false, cleared);
+        }
+        let since = *self.since.get_or_insert(timestamp);
+        if self.reported || timestamp.saturating_sub(since) < persistence {
+            return (false, false);
+        }
+        self.reported = true;
+        (true, false)
+    }
+}
+
+/// Watches for a covered, defocused or redirected camera
+///
+/// The global brightness, the sharpness (variance of the Laplacian) and the
+/// ORB keypoints of the scene are compared with a reference taken at
+/// calibration. A dark image means the lens is covered, a loss of sharpness
+/// that it was sprayed or defocused, and reference keypoints no longer found
+/// where they were that the camera was turned. The checks are mutually
+/// exclusive in that order, since covering a lens also blurs it and hides the
+/// keypoints. A condition is reported once it has lasted `persistence_ms`.
+pub struct SceneHealthMonitor {
+    config: SceneHealthConfig,
+    orb: Ptr<ORB>,
+    reference: Option<SceneReference>,
+    last_check: Option<Duration>,
+    covered: Condition,
+    blurred: Condition,
+    moved: Condition,
+}
+
+impl SceneHealthMonitor {
+    /// Creates a new SceneHealthMonitor without a reference scene
+    pub fn new(config: SceneHealthConfig) -> Result<Self> {
+        let mut orb = ORB::create_def()?;
+        orb.set_max_features(config.keypoints)?;
+        Ok(Self {
+            config,
+            orb,
+            reference: None,
+            last_check: None,
+            covered: Condition::default(),
+            blurred: Condition::default(),
+            moved: Condition::default(),
+        })
+    }
+
+    /// Takes a frame as the reference scene, saving it if a path is configured
+    pub fn set_reference(&mut self, frame: &Mat) -> Result<()> {
+        let reference = SceneReference::capture(frame, &mut self.orb)?;
+        if let Some(path) = self.config.reference_path() {
+            reference.save(path)?;
+        }
+        println!(
+            "Scene reference: brightness = {:.1}, sharpness = {:.1}, {} keypoints",
+            reference.brightness,
+            reference.sharpness,
+            reference.keypoints.len()
+        );
+        self.reference = Some(reference);
+        Ok(())
+    }
+
+    /// Reuses the saved reference scene if it has the given frame size
+    ///
+    /// # Returns
+    /// * `Result<bool>` - Whether a reference was loaded
+    pub fn load_reference(&mut self, frame_width: i32, frame_height: i32) -> Result<bool> {
+        let Some(path) = self.config.reference_path() else {
+            return Ok(false);
+        };
+        let Some(frame) = SceneReference::load_frame(path)? else {
+            return Ok(false);
+        };
+        let reference = SceneReference::capture(&frame, &mut self.orb)?;
+        if reference.size()? != core::Size::new(frame_width, frame_height) {
+            println!("Ignoring scene reference {}: made for a different frame size", path);
+            return Ok(false);
+        }
+        println!("Loaded scene reference from {}", path);
+        self.reference = Some(reference);
+        Ok(true)
+    }
+
+    /// Whether a reference scene is set
+    pub fn has_reference(&self) -> bool {
+        self.reference.is_some()
+    }
+
+    /// Checks one frame, at most once per `check_interval_ms`
+    ///
+    /// # Arguments
+    /// * `frame` - The latest camera frame
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    ///
+    /// # Returns
+    /// * `Result<Vec<TamperEvent>>` - Conditions reported with this frame
+    pub fn process_frame(&mut self, frame: &Mat, timestamp: Duration) -> Result<Vec<TamperEvent>> {
+        let interval = Duration::from_millis(self.config.check_interval_ms);
+        if self.reference.is_none() || self.last_check.is_some_and(|t| timestamp.saturating_sub(t) < interval) {
+            return Ok(Vec::new());
+        }
+        self.last_check = Some(timestamp);
+
+        let gray = grayscale(frame)?;
+        let brightness = core::mean(&gray, &Mat::default())?.0[0];
+        let is_covered = brightness < self.config.min_brightness;
+
+        let sharpness = sharpness(&gray)?;
+        let reference_sharpness = self.reference.as_ref().map_or(0.0, |reference| reference.sharpness);
+        let is_blurred = !is_covered && sharpness < reference_sharpness * self.config.min_sharpness_ratio;
+
+        let matched = if is_covered || is_blurred { None } else { self.match_ratio(&gray)? };
+        let is_moved = matched.is_some_and(|ratio| ratio < self.config.min_match_ratio);
+
+        let persistence = Duration::from_millis(self.config.persistence_ms);
+        let checks = [
+            (self.covered.update(is_covered, timestamp, persistence), TamperEvent::CameraCovered { brightness }),
+            (self.blurred.update(is_blurred, timestamp, persistence), TamperEvent::CameraBlurred { sharpness }),
+            (self.moved.update(is_moved, timestamp, persistence), TamperEvent::CameraMoved { matched: matched.unwrap_or(0.0) }),
+        ];
+
+        let mut events = Vec::new();
+        for ((report, cleared), event) in checks {
+            if report {
+                logging::log_event(&format!("Tamper detected: {}", event))?;
+                events.push(event);
+            }
+            if cleared {
+                logging::log_event(&format!("Scene back to normal after {}", event.kind()))?;
+            }
+        }
+        Ok(events)
+    }
+
+    /// Fraction of the reference keypoints found again close to where they were
+    ///
+    /// # Returns
+    /// * `Result<Option<f64>>` - The fraction, None if the reference scene has
+    ///   too few keypoints to judge
+    fn match_ratio(&mut self, gray: &Mat) -> Result<Option<f64>> {
+        let Some(reference) = self.reference.as_ref() else {
+            return Ok(None);
+        };
+        if reference.keypoints.len() < MIN_REFERENCE_KEYPOINTS {
+            return Ok(None);
+        }
+
+        let (keypoints, descriptors) = features(gray, &mut self.orb)?;
+        if keypoints.is_empty() {
+            return Ok(Some(0.0));
+        }
+
+        let matcher = features2d::BFMatcher::new(core::NORM_HAMMING, true)?;
+        let mut matches = Vector::<DMatch>::new();
+        matcher.train_match(&reference.descriptors, &descriptors, &mut matches, &Mat::default())?;
+
+        let mut in_place = 0;
+        for m in matches.iter() {
+            if m.distance > MAX_DESCRIPTOR_DISTANCE {
+                continue;
+            }
+            let before = reference.keypoints.get(m.query_idx as usize)?.pt();
+            let after = keypoints.get(m.train_idx as usize)?.pt();
+            let shift = ((before.x - after.x) as f64).hypot((before.y - after.y) as f64);
+            if shift <= self.config.max_shift {
+                in_place += 1;
+            }
+        }
+        Ok(Some(in_place as f64 / reference.keypoints.len() as f64))
+    }
+}
+
+fn grayscale(frame: &Mat) -> Result<Mat> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(frame, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+    Ok(gray)
+}
+
+/// Variance of the Laplacian: high for a sharp image, low for a blurred one
+fn sharpness(gray: &Mat) -> Result<f64> {
+    let mut laplacian = Mat::default();
+    imgproc::laplacian_def(gray, &mut laplacian, core::CV_64F)?;
+    let mut mean = Vector::<f64>::new();
+    let mut stddev = Vector::<f64>::new();
+    core::mean_std_dev(&laplacian, &mut mean, &mut stddev, &Mat::default())?;
+    let stddev = stddev.get(0)?;
+    Ok(stddev * stddev)
+}
+
+/// ORB keypoints and descriptors of a grey image
+fn features(gray: &Mat, orb: &mut Ptr<ORB>) -> Result<(Vector<KeyPoint>, Mat)> {
+    let mut keypoints = Vector::<KeyPoint>::new();
+    let mut descriptors = Mat::default();
+    orb.detect_and_compute(gray, &Mat::default(), &mut keypoints, &mut descriptors, false)?;
+    Ok((keypoints, descriptors))
+}
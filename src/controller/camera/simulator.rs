@@ -0,0 +1,423 @@
+use opencv::{
+    core::{self, Mat, Point, Scalar},
+    imgproc,
+    prelude::*,
+};
+use anyhow::Result;
+use std::time::{Duration, SystemTime};
+use crate::controller::camera::source::{FrameSource, DEFAULT_FPS};
+use crate::controller::security::protocol;
+use crate::controller::security::rolling_code::RollingCode;
+use crate::controller::camera::verifier::{ExpectedCode, LedRois, SAMPLE_INTERVAL};
+
+/// One LED drawn by the simulator
+#[derive(Debug, Clone)]
+pub struct SimulatedLed {
+    pub position: Point, // Centre of the LED blob
+    pub radius: i32,     // Radius of the blob in pixels
+    pub color: Scalar,   // Colour when fully lit (BGR)
+    pub pattern: Vec<u8>,// Levels played in a loop, one entry per symbol
+}
+
+/// How the simulated LED controller's clock relates to the camera clock
+#[derive(Debug, Clone, Copy)]
+pub struct TimingModel {
+    pub fps: f64,                   // Camera frame rate
+    pub symbol_duration: Duration,  // How long each pattern entry is shown
+    pub phase_offset: Duration,     // LED clock time at the first frame
+    pub drift: f64,                 // LED clock rate relative to the camera (1.0 = no drift)
+}
+
+impl Default for TimingModel {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            symbol_duration: SAMPLE_INTERVAL,
+            phase_offset: Duration::ZERO,
+            drift: 1.0,
+        }
+    }
+}
+
+/// Appearance of the simulated scene
+#[derive(Debug, Clone, Copy)]
+pub struct SceneConfig {
+    pub width: i32,
+    pub height: i32,
+    pub background: f64,     // Background grey level
+    pub on_brightness: f64,  // Scale applied to the LED colour when lit (0.0 - 1.0)
+    pub off_brightness: f64, // Scale applied to the LED colour when dark (0.0 - 1.0)
+    pub noise_stddev: f64,   // Standard deviation of the per-pixel Gaussian noise
+    pub seed: i32,           // Seed of the noise generator, for reproducible runs
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 480,
+            background: 40.0,
+            on_brightness: 1.0,
+            off_brightness: 0.1,
+            noise_stddev: 2.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Faults the simulator can inject into the footage
+#[derive(Debug, Clone)]
+pub enum Fault {
+    WrongPattern(Vec<Vec<u8>>), // LEDs play these level patterns instead (one per LED)
+    FrozenFrame,                // The last good frame is repeated
+    DroppedFrames,              // Frames are never delivered, time still advances
+    Replay(Duration),           // LEDs show the rolling code from this long ago
+}
+
+/// A fault active during a window of camera time
+#[derive(Debug, Clone)]
+struct ScheduledFault {
+    fault: Fault,
+    start: Duration,
+    end: Duration,
+}
+
+/// Renders blinking LEDs into synthetic frames
+///
+/// The simulator is a `FrameSource`, so calibration and verification can be
+/// driven end to end without a Raspberry Pi or a webcam.
+pub struct LedSimulator {
+    scene: SceneConfig,
+    timing: TimingModel,
+    leds: Vec<SimulatedLed>,
+    levels: u8,                        // Brightness levels per symbol
+    rolling_code: Option<RollingCode>, // Drives the LEDs instead of their looped patterns
+    started_at: SystemTime,            // Wall-clock time of the first frame
+    faults: Vec<ScheduledFault>,
+    frame_index: u64,
+    timestamp: Duration,
+    last_frame: Option<Mat>,
+    frame_limit: Option<u64>,
+}
+
+impl LedSimulator {
+    /// Creates a simulator for the given scene, timing and LEDs
+    pub fn new(scene: SceneConfig, timing: TimingModel, leds: Vec<SimulatedLed>) -> Result<Self> {
+        core::set_rng_seed(scene.seed)?;
+
+        Ok(Self {
+            scene,
+            timing,
+            leds,
+            levels: 2,
+            rolling_code: None,
+            started_at: SystemTime::now(),
+            faults: Vec::new(),
+            frame_index: 0,
+            timestamp: Duration::ZERO,
+            last_frame: None,
+            frame_limit: None,
+        })
+    }
+
+    /// Simulates the LED controller's LEDs inside the default top-right ROIs
+    ///
+    /// # Arguments
+    /// * `scene` - Appearance of the scene
+    /// * `timing` - LED clock relative to the camera
+    /// * `expected` - Code shown by the LEDs; sets their number and levels
+    pub fn from_code(scene: SceneConfig, timing: TimingModel, expected: &ExpectedCode) -> Result<Self> {
+        let rois = LedRois::top_right(scene.width, scene.height, expected.led_count());
+        let levels = expected.levels();
+        let led = |roi: core::Rect, pattern: &[u8]| SimulatedLed {
+            position: Point::new(roi.x + roi.width / 2, roi.y + roi.height / 2),
+            radius: (roi.width.min(roi.height) / 3).max(1),
+            color: Scalar::all(255.0),
+            pattern: pattern.to_vec(),
+        };
+
+        match expected {
+            ExpectedCode::Fixed(patterns) => {
+                // Loop whole protocol frames: preamble, then the payload
+                let frame_length = protocol::frame_length(patterns.len());
+                let leds = rois.rects.iter().enumerate()
+                    .map(|(i, &roi)| {
+                        let framed: Vec<u8> = (0..frame_length)
+                            .map(|index| protocol::frame_symbol(&patterns.leds, index, levels)[i])
+                            .collect();
+                        led(roi, &framed)
+                    })
+                    .collect();
+                Ok(Self::new(scene, timing, leds)?.with_levels(levels))
+            }
+            ExpectedCode::Rolling { code, .. } => {
+                let leds = rois.rects.iter().map(|&roi| led(roi, &[])).collect();
+                Ok(Self::new(scene, timing, leds)?.with_levels(levels).with_rolling_code(code.clone()))
+            }
+        }
+    }
+
+    /// Sets the number of brightness levels per symbol (defaults to on/off)
+    pub fn with_levels(mut self, levels: u8) -> Self {
+        self.levels = levels.max(2);
+        self
+    }
+
+    /// Drives the LEDs with a rolling code instead of their looped patterns
+    ///
+    /// LED `i` shows symbol `i` of the code at the simulated wall-clock time.
+    pub fn with_rolling_code(mut self, code: RollingCode) -> Self {
+        self.rolling_code = Some(code);
+        self
+    }
+
+    /// Sets the wall-clock time of the first frame (defaults to now)
+    pub fn with_start_time(mut self, started_at: SystemTime) -> Self {
+        self.started_at = started_at;
+        self
+    }
+
+    /// Injects `fault` between `start` and `end` of camera time
+    pub fn with_fault(mut self, fault: Fault, start: Duration, end: Duration) -> Self {
+        self.faults.push(ScheduledFault { fault, start, end });
+        self
+    }
+
+    /// Stops the simulation after `frames` frames
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frame_limit = Some(frames);
+        self
+    }
+
+    /// Time on the LED controller's clock at a camera timestamp
+    fn led_time(&self, timestamp: Duration) -> Duration {
+        self.timing.phase_offset + timestamp.mul_f64(self.timing.drift)
+    }
+
+    /// The LED symbol index shown at a camera timestamp
+    fn symbol_index(&self, timestamp: Duration) -> u64 {
+        (self.led_time(timestamp).as_secs_f64() / self.timing.symbol_duration.as_secs_f64()).floor() as u64
+    }
+
+    /// Brightness level of LED `led` at a camera timestamp
+    fn level(&self, led: usize, timestamp: Duration) -> Result<u8> {
+        let fault = self.active_fault(timestamp);
+
+        if let Some(Fault::WrongPattern(patterns)) = fault {
+            if let Some(pattern) = patterns.get(led) {
+                return Ok(looped_symbol(pattern, self.symbol_index(timestamp)));
+            }
+        }
+
+        if let Some(code) = &self.rolling_code {
+            let mut time = self.started_at + self.led_time(timestamp);
+            if let Some(Fault::Replay(age)) = fault {
+                time -= *age;
+            }
+            return Ok(code.symbols_at(time)?.get(led).copied().unwrap_or(0));
+        }
+
+        Ok(looped_symbol(&self.leds[led].pattern, self.symbol_index(timestamp)))
+    }
+
+    fn active_fault(&self, timestamp: Duration) -> Option<&Fault> {
+        self.faults.iter()
+            .find(|f| timestamp >= f.start && timestamp < f.end)
+            .map(|f| &f.fault)
+    }
+
+    /// Draws the scene as it looks at `timestamp`
+    fn render(&self, timestamp: Duration) -> Result<Mat> {
+        let mut frame = Mat::new_rows_cols_with_default(
+            self.scene.height,
+            self.scene.width,
+            core::CV_8UC3,
+            Scalar::all(self.scene.background),
+        )?;
+
+        for (i, led) in self.leds.iter().enumerate() {
+            // Levels are spread evenly between the off and fully lit brightness
+            let fraction = self.level(i, timestamp)?.min(self.levels - 1) as f64 / (self.levels - 1) as f64;
+            let scale = self.scene.off_brightness + (self.scene.on_brightness - self.scene.off_brightness) * fraction;
+            let color = Scalar::new(led.color[0] * scale, led.color[1] * scale, led.color[2] * scale, 0.0);
+
+            imgproc::circle(&mut frame, led.position, led.radius, color, imgproc::FILLED, imgproc::LINE_AA, 0)?;
+        }
+
+        if self.scene.noise_stddev > 0.0 {
+            add_noise(&mut frame, self.scene.noise_stddev)?;
+        }
+
+        Ok(frame)
+    }
+}
+
+/// Entry of a looped pattern at a symbol index (0 for an empty pattern)
+fn looped_symbol(pattern: &[u8], symbol: u64) -> u8 {
+    if pattern.is_empty() {
+        return 0;
+    }
+    pattern[(symbol % pattern.len() as u64) as usize]
+}
+
+/// Adds zero-mean Gaussian noise to an 8-bit frame
+fn add_noise(frame: &mut Mat, stddev: f64) -> Result<()> {
+    let mut wide = Mat::default();
+    frame.convert_to_def(&mut wide, core::CV_16SC3)?;
+
+    let mut noise = Mat::new_rows_cols_with_default(frame.rows(), frame.cols(), core::CV_16SC3, Scalar::all(0.0))?;
+    core::randn(&mut noise, &Scalar::all(0.0), &Scalar::all(stddev))?;
+
+    let mut noisy = Mat::default();
+    core::add_def(&wide, &noise, &mut noisy)?;
+    noisy.convert_to_def(frame, core::CV_8UC3)?; // Saturates back into 0-255
+    Ok(())
+}
+
+impl FrameSource for LedSimulator {
+    fn read_frame(&mut self) -> Result<Option<Mat>> {
+        loop {
+            if self.frame_limit.is_some_and(|limit| self.frame_index >= limit) {
+                return Ok(None);
+            }
+
+            let timestamp = Duration::from_secs_f64(self.frame_index as f64 / self.timing.fps);
+            self.frame_index += 1;
+
+            let frame = match (self.active_fault(timestamp), &self.last_frame) {
+                (Some(Fault::DroppedFrames), _) => continue,
+                (Some(Fault::FrozenFrame), Some(last)) => last.clone(),
+                _ => self.render(timestamp)?,
+            };
+
+            self.timestamp = timestamp;
+            self.last_frame = Some(frame.clone());
+            return Ok(Some(frame));
+        }
+    }
+
+    fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    fn fps(&self) -> f64 {
+        self.timing.fps
+    }
+
+    fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::controller::camera::policy::VerificationState;
+    use crate::controller::camera::verdict::VerdictStatus;
+    use crate::controller::camera::verifier::{Calibration, ExpectedPatterns, LedVerifier};
+    use crate::controller::security::secure_pattern_generator::PatternConfig;
+
+    const SCENE: SceneConfig = SceneConfig {
+        width: 320,
+        height: 240,
+        background: 40.0,
+        on_brightness: 1.0,
+        off_brightness: 0.1,
+        noise_stddev: 2.0,
+        seed: 0,
+    };
+
+    fn timing() -> TimingModel {
+        TimingModel { fps: 10.0, ..TimingModel::default() }
+    }
+
+    fn expected(secret: &[u8]) -> ExpectedCode {
+        ExpectedCode::Fixed(ExpectedPatterns::from_secret(&PatternConfig::default(), secret).unwrap())
+    }
+
+    /// Outcome of running a simulation through the verifier
+    struct Run {
+        states: Vec<VerificationState>, // States entered, in order
+        final_state: VerificationState,
+        final_verdict: VerdictStatus,
+    }
+
+    /// Calibrates on clean footage, then verifies `frame_periods` LED frames of
+    /// footage with `fault` injected from `fault_start` to `fault_end` LED frames in
+    fn run(fault: Option<Fault>, fault_start: u32, fault_end: u32, frame_periods: u32) -> Result<Run> {
+        let expected = expected(b"simulator test secret");
+        let period = expected.frame_period();
+        let rois = LedRois::top_right(SCENE.width, SCENE.height, expected.led_count());
+
+        let mut calibration = Calibration::new(rois.clone());
+        let mut clean = LedSimulator::from_code(SCENE, timing(), &expected)?
+            .with_frame_limit((2.0 * period.as_secs_f64() * timing().fps) as u64);
+        while let Some(frame) = clean.read_frame()? {
+            calibration.add_sample(&frame)?;
+        }
+        let thresholds = calibration.finish().expect("simulated LEDs calibrate");
+
+        let mut simulator = LedSimulator::from_code(SCENE, timing(), &expected)?
+            .with_frame_limit((frame_periods as f64 * period.as_secs_f64() * timing().fps) as u64);
+        if let Some(fault) = fault {
+            simulator = simulator.with_fault(fault, period * fault_start, period * fault_end);
+        }
+
+        let mut verifier = LedVerifier::new(rois, thresholds, expected);
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let hook_states = Arc::clone(&states);
+        verifier.on_transition(move |transition| hook_states.lock().unwrap().push(transition.to));
+
+        let mut verdict = verifier.verdict();
+        while let Some(frame) = simulator.read_frame()? {
+            verdict = verifier.process_frame(&frame, simulator.timestamp(), simulator.capture_time())?;
+        }
+
+        let states = states.lock().unwrap().clone();
+        Ok(Run { states, final_state: verifier.state(), final_verdict: verdict.status })
+    }
+
+    #[test]
+    fn clean_footage_verifies() -> Result<()> {
+        let run = run(None, 0, 0, 6)?;
+        assert_eq!(run.final_state, VerificationState::Verified);
+        assert_eq!(run.final_verdict, VerdictStatus::Verified);
+        assert!(!run.states.contains(&VerificationState::Suspect));
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_pattern_is_unverified_and_raises_tamper() -> Result<()> {
+        // A clean-looking code from another secret, preamble included
+        let ExpectedCode::Fixed(other) = expected(b"another secret") else { unreachable!() };
+        let frame_length = protocol::frame_length(other.len());
+        let wrong = (0..other.leds.len())
+            .map(|led| (0..frame_length).map(|index| protocol::frame_symbol(&other.leds, index, other.levels)[led]).collect())
+            .collect();
+
+        let run = run(Some(Fault::WrongPattern(wrong)), 3, 12, 12)?;
+        assert!(run.states.contains(&VerificationState::Verified));
+        assert_eq!(run.final_state, VerificationState::Tampered);
+        assert_eq!(run.final_verdict, VerdictStatus::Unverified);
+        Ok(())
+    }
+
+    #[test]
+    fn frozen_frame_raises_tamper() -> Result<()> {
+        let run = run(Some(Fault::FrozenFrame), 3, 12, 12)?;
+        assert!(run.states.contains(&VerificationState::Verified));
+        assert_eq!(run.final_state, VerificationState::Tampered);
+        assert_eq!(run.final_verdict, VerdictStatus::Indeterminate);
+        Ok(())
+    }
+
+    #[test]
+    fn dropped_frames_make_the_footage_suspect() -> Result<()> {
+        let run = run(Some(Fault::DroppedFrames), 3, 6, 8)?;
+        let verified = run.states.iter().position(|&state| state == VerificationState::Verified);
+        let suspect = run.states.iter().rposition(|&state| state == VerificationState::Suspect);
+        assert!(verified.is_some() && suspect.is_some_and(|suspect| Some(suspect) > verified), "states {:?}", run.states);
+        Ok(())
+    }
+}
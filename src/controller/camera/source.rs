@@ -0,0 +1,412 @@
+use opencv::{
+    core::{self, Mat, Rect, Scalar},
+    imgcodecs,
+    imgproc,
+    prelude::*,
+    videoio::{self, VideoCapture},
+};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// Frame rate assumed when a backend cannot report one
+pub const DEFAULT_FPS: f64 = 30.0;
+
+// Image extensions picked up by ImageSequenceSource
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "bmp", "tif"];
+
+/// A stream of frames feeding the camera pipeline
+///
+/// Timestamps are measured on the source's own clock, starting at zero, so
+/// recorded or synthetic footage is processed exactly as it was captured.
+/// Wall-clock capture times are anchored at the moment the source was opened,
+/// so replayed footage is checked against the current time, not its own.
+pub trait FrameSource {
+    /// Reads the next frame
+    ///
+    /// # Returns
+    /// * `Result<Option<Mat>>` - Next frame, or None once the source is exhausted
+    fn read_frame(&mut self) -> Result<Option<Mat>>;
+
+    /// Capture time of the most recently read frame
+    fn timestamp(&self) -> Duration;
+
+    /// Nominal frame rate of the source
+    fn fps(&self) -> f64;
+
+    /// Wall-clock time corresponding to timestamp zero
+    fn started_at(&self) -> SystemTime;
+
+    /// Wall-clock capture time of the most recently read frame
+    fn capture_time(&self) -> SystemTime {
+        self.started_at() + self.timestamp()
+    }
+}
+
+/// Opens a local camera device
+///
+/// # Arguments
+/// * `camera_index` - Index of the camera device
+///
+/// # Returns
+/// * `Result<VideoCapture>` - Opened capture device or error
+pub fn open_camera(camera_index: i32) -> Result<VideoCapture> {
+    let cap = VideoCapture::new(camera_index, videoio::CAP_ANY)
+        .context("Failed to open camera")?;
+
+    if !cap.is_opened()? {
+        return Err(anyhow::anyhow!("Could not open camera at index {}", camera_index));
+    }
+
+    Ok(cap)
+}
+
+/// Opens a frame source from a textual description
+///
+/// Supported forms are `camera:<index>`, `file:<path>`, `images:<directory>`
+/// and `synthetic`. A bare number is treated as a camera index.
+///
+/// # Arguments
+/// * `spec` - Source description, e.g. from the command line
+///
+/// # Returns
+/// * `Result<Box<dyn FrameSource>>` - Opened source or error
+pub fn open_source(spec: &str) -> Result<Box<dyn FrameSource>> {
+    if let Ok(index) = spec.parse::<i32>() {
+        return Ok(Box::new(CameraSource::new(index)?));
+    }
+
+    let (kind, arg) = spec.split_once(':').unwrap_or((spec, ""));
+    match kind {
+        "camera" => {
+            let index = arg.parse().context(format!("Invalid camera index: {}", arg))?;
+            Ok(Box::new(CameraSource::new(index)?))
+        }
+        "file" => Ok(Box::new(VideoFileSource::new(arg)?)),
+        "images" => Ok(Box::new(ImageSequenceSource::new(arg, DEFAULT_FPS)?)),
+        "synthetic" => Ok(Box::new(SyntheticSource::new(640, 480, DEFAULT_FPS))),
+        _ => Err(anyhow::anyhow!("Unknown frame source: {}", spec)),
+    }
+}
+
+/// Live frames from a local camera
+pub struct CameraSource {
+    cap: VideoCapture,
+    started: Instant,    // When the camera was opened
+    started_at: SystemTime,
+    timestamp: Duration, // Time of the last frame since `started`
+    fps: f64,
+}
+
+impl CameraSource {
+    /// Opens the camera at the given index
+    pub fn new(camera_index: i32) -> Result<Self> {
+        let cap = open_camera(camera_index)?;
+        let fps = cap.get(videoio::CAP_PROP_FPS).unwrap_or(0.0);
+
+        Ok(Self {
+            cap,
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            timestamp: Duration::ZERO,
+            fps: if fps > 0.0 { fps } else { DEFAULT_FPS },
+        })
+    }
+}
+
+impl FrameSource for CameraSource {
+    fn read_frame(&mut self) -> Result<Option<Mat>> {
+        let mut frame = Mat::default();
+        self.cap.read(&mut frame)?;
+        self.timestamp = self.started.elapsed();
+
+        // An empty frame means the camera stopped delivering
+        if frame.empty() {
+            return Ok(None);
+        }
+        Ok(Some(frame))
+    }
+
+    fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+}
+
+/// Frames decoded from a video file, as fast as they can be read
+pub struct VideoFileSource {
+    cap: VideoCapture,
+    started_at: SystemTime,
+    frame_index: u64,
+    timestamp: Duration,
+    fps: f64,
+}
+
+impl VideoFileSource {
+    /// Opens the video file at `path`
+    pub fn new(path: &str) -> Result<Self> {
+        let cap = VideoCapture::from_file(path, videoio::CAP_ANY)
+            .context(format!("Failed to open video file: {}", path))?;
+        if !cap.is_opened()? {
+            return Err(anyhow::anyhow!("Could not open video file: {}", path));
+        }
+
+        let fps = cap.get(videoio::CAP_PROP_FPS).unwrap_or(0.0);
+
+        Ok(Self {
+            cap,
+            started_at: SystemTime::now(),
+            frame_index: 0,
+            timestamp: Duration::ZERO,
+            fps: if fps > 0.0 { fps } else { DEFAULT_FPS },
+        })
+    }
+}
+
+impl FrameSource for VideoFileSource {
+    fn read_frame(&mut self) -> Result<Option<Mat>> {
+        let mut frame = Mat::default();
+        if !self.cap.read(&mut frame)? || frame.empty() {
+            return Ok(None);
+        }
+
+        // Prefer the container's timestamp, fall back to the nominal frame rate
+        let position_ms = self.cap.get(videoio::CAP_PROP_POS_MSEC).unwrap_or(0.0);
+        self.timestamp = if position_ms > 0.0 {
+            Duration::from_secs_f64(position_ms / 1000.0)
+        } else {
+            Duration::from_secs_f64(self.frame_index as f64 / self.fps)
+        };
+        self.frame_index += 1;
+
+        Ok(Some(frame))
+    }
+
+    fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+}
+
+/// Frames loaded from a directory of images, in file name order
+pub struct ImageSequenceSource {
+    paths: Vec<PathBuf>,
+    started_at: SystemTime,
+    next: usize,
+    fps: f64,
+}
+
+impl ImageSequenceSource {
+    /// Lists the images in `dir`, played back at `fps`
+    pub fn new(dir: &str, fps: f64) -> Result<Self> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).context(format!("Failed to read image directory: {}", dir))? {
+            let path = entry?.path();
+            if is_image(&path) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        if paths.is_empty() {
+            return Err(anyhow::anyhow!("No images found in {}", dir));
+        }
+
+        Ok(Self { paths, started_at: SystemTime::now(), next: 0, fps })
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+impl FrameSource for ImageSequenceSource {
+    fn read_frame(&mut self) -> Result<Option<Mat>> {
+        let Some(path) = self.paths.get(self.next) else {
+            return Ok(None);
+        };
+
+        let frame = imgcodecs::imread(&path.to_string_lossy(), imgcodecs::IMREAD_COLOR)?;
+        if frame.empty() {
+            return Err(anyhow::anyhow!("Failed to decode image: {}", path.display()));
+        }
+
+        self.next += 1;
+        Ok(Some(frame))
+    }
+
+    fn timestamp(&self) -> Duration {
+        Duration::from_secs_f64(self.next.saturating_sub(1) as f64 / self.fps)
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+}
+
+/// Renders one synthetic frame: frame index, timestamp and the frame to draw into
+pub type FrameRenderer = Box<dyn FnMut(u64, Duration, &mut Mat) -> Result<()>>;
+
+/// Generated frames for running the pipeline without a camera
+///
+/// By default a white square sweeps across a grey background, which is enough
+/// to exercise motion detection.
+pub struct SyntheticSource {
+    width: i32,
+    height: i32,
+    fps: f64,
+    started_at: SystemTime,
+    frame_index: u64,
+    frame_limit: Option<u64>,
+    renderer: FrameRenderer,
+}
+
+impl SyntheticSource {
+    /// Creates an endless synthetic source
+    pub fn new(width: i32, height: i32, fps: f64) -> Self {
+        Self {
+            width,
+            height,
+            fps,
+            started_at: SystemTime::now(),
+            frame_index: 0,
+            frame_limit: None,
+            renderer: Box::new(move |index, _, frame| {
+                let size = 40;
+                let x = (index as i32 * 4) % (width + size) - size;
+                imgproc::rectangle(
+                    frame,
+                    Rect::new(x, height / 2 - size / 2, size, size),
+                    Scalar::all(255.0),
+                    imgproc::FILLED,
+                    imgproc::LINE_8,
+                    0,
+                )?;
+                Ok(())
+            }),
+        }
+    }
+
+    /// Stops the source after `frames` frames
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frame_limit = Some(frames);
+        self
+    }
+
+    /// Replaces the default renderer
+    ///
+    /// The renderer draws onto a mid-grey frame of the configured size.
+    pub fn with_renderer(mut self, renderer: FrameRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+}
+
+impl FrameSource for SyntheticSource {
+    fn read_frame(&mut self) -> Result<Option<Mat>> {
+        if self.frame_limit.is_some_and(|limit| self.frame_index >= limit) {
+            return Ok(None);
+        }
+
+        let timestamp = Duration::from_secs_f64(self.frame_index as f64 / self.fps);
+        let mut frame = Mat::new_rows_cols_with_default(
+            self.height,
+            self.width,
+            core::CV_8UC3,
+            Scalar::all(128.0),
+        )?;
+        (self.renderer)(self.frame_index, timestamp, &mut frame)?;
+
+        self.frame_index += 1;
+        Ok(Some(frame))
+    }
+
+    fn timestamp(&self) -> Duration {
+        Duration::from_secs_f64(self.frame_index.saturating_sub(1) as f64 / self.fps)
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_source_stops_at_its_frame_limit() {
+        let mut source = SyntheticSource::new(64, 48, 10.0).with_frame_limit(3);
+        for _ in 0..3 {
+            let frame = source.read_frame().unwrap().unwrap();
+            assert_eq!((frame.cols(), frame.rows()), (64, 48));
+        }
+        assert!(source.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn synthetic_timestamps_advance_by_one_frame_period() {
+        let mut source = SyntheticSource::new(64, 48, 10.0);
+        let mut previous = None;
+        for index in 0..20 {
+            source.read_frame().unwrap().unwrap();
+            assert!((source.timestamp().as_secs_f64() - index as f64 * 0.1).abs() < 1e-6);
+            assert!(previous.is_none_or(|previous| source.timestamp() > previous));
+            assert_eq!(source.capture_time(), source.started_at() + source.timestamp());
+            previous = Some(source.timestamp());
+        }
+    }
+
+    #[test]
+    fn unknown_source_specs_are_rejected() {
+        assert!(open_source("bogus").is_err());
+        assert!(open_source("camera:front").is_err());
+        assert!(open_source("images:/nonexistent/frames").is_err());
+    }
+
+    #[test]
+    fn image_sequence_plays_images_in_file_name_order() {
+        let dir = std::env::temp_dir().join(format!("image_sequence_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Written out of order, each image identified by its width
+        for (name, width) in [("frame_2.png", 30), ("frame_0.png", 10), ("frame_1.jpg", 20)] {
+            let image = Mat::new_rows_cols_with_default(8, width, core::CV_8UC3, Scalar::all(128.0)).unwrap();
+            imgcodecs::imwrite(&dir.join(name).to_string_lossy(), &image, &core::Vector::new()).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+        let mut source = ImageSequenceSource::new(&dir.to_string_lossy(), 10.0).unwrap();
+        let mut widths = Vec::new();
+        while let Some(frame) = source.read_frame().unwrap() {
+            widths.push(frame.cols());
+        }
+        assert_eq!(widths, vec![10, 20, 30]);
+        assert!((source.timestamp().as_secs_f64() - 0.2).abs() < 1e-6);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,40 @@
-use opencv::prelude::*;
+use opencv::{
+    core::{Mat, Size},
+    prelude::*,
+    videoio::VideoWriter,
+};
 use chrono::Local;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
+use crate::controller::camera::logging;
+
+// Prefix and suffix of clips still being recorded
+const PARTIAL_PREFIX: &str = ".recording_";
+const PARTIAL_SUFFIX: &str = ".partial.avi";
+
+// Start time in clip and segment names, to the millisecond so recordings
+// started in the same second get names of their own
+pub const FILE_TIME_FORMAT: &str = "%Y%m%d_%H%M%S_%3f";
+
+/// Moves a finished or recovered recording to its final name
+///
+/// Refuses to replace a file already there, so a clip flagged as evidence
+/// can never be overwritten by a later one; the recording is then left at
+/// `from`.
+pub fn move_recording(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(anyhow::anyhow!("Refusing to overwrite {} with {}", to.display(), from.display()));
+    }
+    // Same directory, so the recording appears complete or not at all
+    std::fs::rename(from, to).context(format!("Failed to move {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
 
 /// Stores video frames to disk with timestamped filenames
 pub struct VideoStorage {
     output_dir: String,
     frame_counter: u32,
+    recovered: Vec<String>, // Interrupted recordings recovered on start
 }
 
 impl VideoStorage {
@@ -22,9 +50,72 @@ impl VideoStorage {
         std::fs::create_dir_all(output_dir)
             .context(format!("Failed to create output directory: {}", output_dir))?;
         
-        Ok(Self {
+        let mut storage = Self {
             output_dir: output_dir.to_string(),
             frame_counter: 0,
+            recovered: Vec::new(),
+        };
+        storage.recover_partial_recordings()?;
+        Ok(storage)
+    }
+
+    /// Keeps the clips left half-written by a crash
+    ///
+    /// They are renamed like finished clips with an `interrupted` event type;
+    /// everything up to the last frame written is usually still playable.
+    fn recover_partial_recordings(&mut self) -> Result<()> {
+        let entries = std::fs::read_dir(&self.output_dir)
+            .context(format!("Failed to read output directory: {}", self.output_dir))?;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(timestamp) = name.strip_prefix(PARTIAL_PREFIX).and_then(|rest| rest.strip_suffix(PARTIAL_SUFFIX)) else {
+                continue;
+            };
+            let recovered = Path::new(&self.output_dir).join(format!("video_{}_motion_event_interrupted.avi", timestamp));
+            move_recording(&path, &recovered).context("Failed to recover partial recording")?;
+            logging::log_event(&format!("Recovered interrupted recording: {}", recovered.display()))?;
+            self.recovered.push(recovered.to_string_lossy().to_string());
+        }
+        Ok(())
+    }
+
+    /// Takes the paths of the interrupted recordings recovered on start
+    pub fn take_recovered(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.recovered)
+    }
+
+    /// Starts recording an event clip that frames are written to as they arrive
+    ///
+    /// # Arguments
+    /// * `frame_size` - Size of the frames
+    /// * `fps` - Frames per second for output video
+    ///
+    /// # Returns
+    /// * `Result<EventRecorder>` - Recorder writing to a temporary file until finished
+    pub fn start_event(&self, frame_size: Size, fps: f64) -> Result<EventRecorder> {
+        let timestamp = Local::now().format(FILE_TIME_FORMAT).to_string();
+        let partial_path = Path::new(&self.output_dir).join(format!("{}{}{}", PARTIAL_PREFIX, timestamp, PARTIAL_SUFFIX));
+
+        let writer = VideoWriter::new(
+            &partial_path.to_string_lossy(),
+            VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+            fps,
+            frame_size,
+            true
+        ).context("Failed to create video writer")?;
+        if !writer.is_opened()? {
+            return Err(anyhow::anyhow!("Failed to open video writer: {}", partial_path.display()));
+        }
+
+        Ok(EventRecorder {
+            writer,
+            partial_path,
+            output_dir: self.output_dir.clone(),
+            timestamp,
+            frames: 0,
         })
     }
 
@@ -60,46 +151,59 @@ impl VideoStorage {
         self.frame_counter += 1;
         Ok(filename)
     }
+}
 
-    /// Saves a sequence of frames as a video file
-    /// 
+/// Event clip being written frame by frame
+///
+/// Frames go to a hidden temporary file as they arrive, so a long event does
+/// not have to fit in memory. The clip only gets its final name, which carries
+/// the event type, once it is finished; until then a crash leaves the partial
+/// file behind for `VideoStorage` to recover on the next start.
+pub struct EventRecorder {
+    writer: VideoWriter,
+    partial_path: PathBuf, // Temporary file the frames are written to
+    output_dir: String,
+    timestamp: String,     // Start time of the event, used in the final file name
+    frames: usize,         // Frames written so far
+}
+
+impl EventRecorder {
+    /// Appends a frame to the clip
+    pub fn write(&mut self, frame: &Mat) -> Result<()> {
+        self.writer.write(frame).context("Failed to write event frame")?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames written so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Closes the clip and moves it to its final name
+    ///
     /// # Arguments
-    /// * `frames` - Vector of frames to save
     /// * `event_type` - Description of the event
-    /// * `fps` - Frames per second for output video
-    /// 
+    ///
     /// # Returns
     /// * `Result<String>` - Path to saved video file
-    pub fn save_video(&self, frames: &[Mat], event_type: &str, fps: f64) -> Result<String> {
-        if frames.is_empty() {
-            return Err(anyhow::anyhow!("No frames to save"));
-        }
-
-        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    pub fn finish(mut self, event_type: &str) -> Result<String> {
+        self.writer.release()?;
         let filename = format!(
             "{}/video_{}_{}.avi",
             self.output_dir,
-            timestamp,
+            self.timestamp,
             event_type.replace(" ", "_")
         );
-
-        // Get frame size from first frame
-        let frame_size = frames[0].size()?;
-        
-        // Create video writer
-        let mut writer = opencv::videoio::VideoWriter::new(
-            &filename,
-            opencv::videoio::VideoWriter::fourcc('M', 'J', 'P', 'G')?,
-            fps,
-            frame_size,
-            true
-        ).context("Failed to create video writer")?;
-
-        // Write each frame
-        for frame in frames {
-            writer.write(frame)?;
-        }
-
+        move_recording(&self.partial_path, Path::new(&filename)).context("Failed to finalise video")?;
         Ok(filename)
     }
-}
\ No newline at end of file
+
+    /// Closes the clip and deletes it, e.g. for an event that was too short
+    pub fn discard(mut self) -> Result<()> {
+        self.writer.release()?;
+        std::fs::remove_file(&self.partial_path)
+            .context(format!("Failed to delete partial video: {}", self.partial_path.display()))?;
+        Ok(())
+    }
+}
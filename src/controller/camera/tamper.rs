@@ -0,0 +1,271 @@
+use opencv::{
+    core::{self, Mat, Size},
+    imgproc,
+    prelude::*,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+use crate::controller::camera::logging;
+
+// Size of the image a perceptual hash is computed from: one more column than
+// rows, so each row gives 8 left/right comparisons and the hash has 64 bits
+const HASH_SIZE: Size = Size { width: 9, height: 8 };
+
+/// Tampering with the video feed or the camera itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TamperEvent {
+    FrozenFrame { duration: Duration }, // The same frame has been delivered for this long
+    LoopedFootage { period: Duration }, // The latest frames repeat a sequence from this long ago
+    TimestampGap { gap: Duration },     // No frame arrived for this long
+    TimestampReversed { by: Duration }, // A frame is timestamped this much earlier than its predecessor
+    CameraCovered { brightness: f64 },  // The image is this dark (mean grey level)
+    CameraBlurred { sharpness: f64 },   // The image has lost its sharpness (Laplacian variance)
+    CameraMoved { matched: f64 },       // Only this fraction of the reference keypoints is still in place
+}
+
+impl TamperEvent {
+    /// Short name of the kind of tampering
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TamperEvent::FrozenFrame { .. } => "frozen frame",
+            TamperEvent::LoopedFootage { .. } => "looped footage",
+            TamperEvent::TimestampGap { .. } => "timestamp gap",
+            TamperEvent::TimestampReversed { .. } => "timestamp reversed",
+            TamperEvent::CameraCovered { .. } => "camera covered",
+            TamperEvent::CameraBlurred { .. } => "camera blurred",
+            TamperEvent::CameraMoved { .. } => "camera moved",
+        }
+    }
+}
+
+impl fmt::Display for TamperEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TamperEvent::FrozenFrame { duration } => {
+                write!(f, "frozen frame for {:.1}s", duration.as_secs_f64())
+            }
+            TamperEvent::LoopedFootage { period } => {
+                write!(f, "looped footage repeating every {:.1}s", period.as_secs_f64())
+            }
+            TamperEvent::TimestampGap { gap } => {
+                write!(f, "no frames for {:.2}s", gap.as_secs_f64())
+            }
+            TamperEvent::TimestampReversed { by } => {
+                write!(f, "frame timestamp went back by {:.2}s", by.as_secs_f64())
+            }
+            TamperEvent::CameraCovered { brightness } => {
+                write!(f, "camera covered (brightness {:.1})", brightness)
+            }
+            TamperEvent::CameraBlurred { sharpness } => {
+                write!(f, "camera blurred or defocused (sharpness {:.1})", sharpness)
+            }
+            TamperEvent::CameraMoved { matched } => {
+                write!(f, "camera moved ({:.0}% of the reference scene in place)", matched * 100.0)
+            }
+        }
+    }
+}
+
+/// Settings of the frozen-frame, loop and timestamp checks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TamperConfig {
+    pub enabled: bool,
+    pub freeze_ms: u64,             // How long identical frames must last to count as frozen
+    pub freeze_max_difference: f64, // Mean absolute pixel difference up to which two frames are identical
+    pub loop_frames: usize,         // Length of the frame sequence looked for in the history
+    pub loop_history: usize,        // Frames of hash history searched for repeats
+    pub loop_max_distance: u32,     // Hash bits two frames may differ by and still match
+    pub loop_min_changes: usize,    // Frame-to-frame changes the sequence needs, so a still scene is not a loop
+    pub max_gap_frames: f64,        // Missing frame intervals that count as a timestamp gap
+}
+
+impl Default for TamperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            freeze_ms: 3000,
+            freeze_max_difference: 0.1,
+            loop_frames: 30,
+            loop_history: 3000,
+            loop_max_distance: 4,
+            loop_min_changes: 3,
+            max_gap_frames: 5.0,
+        }
+    }
+}
+
+/// 64-bit difference hash of a frame
+///
+/// The frame is reduced to 9x8 grey cells and every bit says whether a cell
+/// is brighter than its right neighbour, so the hash survives sensor noise
+/// and compression but changes when the scene does.
+pub fn perceptual_hash(frame: &Mat) -> Result<u64> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(frame, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+    let mut cells = Mat::default();
+    imgproc::resize(&gray, &mut cells, HASH_SIZE, 0.0, 0.0, imgproc::INTER_AREA)?;
+
+    let width = HASH_SIZE.width as usize;
+    let mut hash = 0u64;
+    for row in cells.data_typed::<u8>()?.chunks(width) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | (pair[0] > pair[1]) as u64;
+        }
+    }
+    Ok(hash)
+}
+
+/// Number of bits two perceptual hashes differ in
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Detects a frozen, looping or stalling video feed
+///
+/// Runs alongside `MotionDetector` on every frame. A freeze is a run of
+/// frames that are identical down to the sensor noise; a loop is a moving
+/// sequence of frames whose perceptual hashes repeat an earlier stretch of
+/// the history; a gap is a pause between frame timestamps far beyond the
+/// nominal frame interval. Every detection is written to the security log.
+pub struct TamperDetector {
+    config: TamperConfig,
+    previous_frame: Option<Mat>,       // Last frame, compared exactly to spot freezes
+    previous_time: Option<Duration>,   // Timestamp of the last frame
+    frozen_since: Option<Duration>,    // Timestamp of the first frame of the current freeze
+    freeze_reported: bool,
+    hashes: VecDeque<(Duration, u64)>, // Timestamp and perceptual hash of the recent frames, oldest first
+    in_loop: bool,                     // A loop has been reported and is still matching
+}
+
+impl TamperDetector {
+    /// Creates a new TamperDetector
+    pub fn new(config: TamperConfig) -> Self {
+        Self {
+            config,
+            previous_frame: None,
+            previous_time: None,
+            frozen_since: None,
+            freeze_reported: false,
+            hashes: VecDeque::new(),
+            in_loop: false,
+        }
+    }
+
+    /// Checks one frame
+    ///
+    /// # Arguments
+    /// * `frame` - The latest camera frame
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    /// * `fps` - Nominal frame rate of the source, 0 if unknown
+    ///
+    /// # Returns
+    /// * `Result<Vec<TamperEvent>>` - Tampering detected with this frame
+    pub fn process_frame(&mut self, frame: &Mat, timestamp: Duration, fps: f64) -> Result<Vec<TamperEvent>> {
+        let mut events = Vec::new();
+        let hash = perceptual_hash(frame)?;
+
+        events.extend(self.check_timestamp(timestamp, fps));
+        events.extend(self.check_freeze(frame, hash, timestamp)?);
+        events.extend(self.check_loop(hash, timestamp));
+
+        for event in &events {
+            logging::log_event(&format!("Tamper detected: {}", event))?;
+        }
+
+        self.previous_frame = Some(frame.clone());
+        self.previous_time = Some(timestamp);
+        Ok(events)
+    }
+
+    /// Flags timestamps that jump back or leave a gap of several frame intervals
+    fn check_timestamp(&self, timestamp: Duration, fps: f64) -> Option<TamperEvent> {
+        let previous = self.previous_time?;
+        if timestamp < previous {
+            return Some(TamperEvent::TimestampReversed { by: previous - timestamp });
+        }
+
+        let gap = timestamp - previous;
+        (fps > 0.0 && gap.as_secs_f64() > self.config.max_gap_frames / fps)
+            .then_some(TamperEvent::TimestampGap { gap })
+    }
+
+    /// Reports a freeze once it has lasted `freeze_ms`, and logs when it ends
+    fn check_freeze(&mut self, frame: &Mat, hash: u64, timestamp: Duration) -> Result<Option<TamperEvent>> {
+        let identical = match (&self.previous_frame, self.hashes.back()) {
+            (Some(previous), Some(&(_, previous_hash))) if previous_hash == hash && previous.size()? == frame.size()? => {
+                let pixels = (frame.total() * frame.channels() as usize).max(1) as f64;
+                core::norm2(previous, frame, core::NORM_L1, &Mat::default())? / pixels <= self.config.freeze_max_difference
+            }
+            _ => false,
+        };
+
+        if !identical {
+            if self.freeze_reported {
+                let duration = self.frozen_duration(timestamp);
+                logging::log_event(&format!("Frozen frame ended after {:.1}s", duration.as_secs_f64()))?;
+            }
+            self.frozen_since = None;
+            self.freeze_reported = false;
+            return Ok(None);
+        }
+
+        let since = *self.frozen_since.get_or_insert(self.previous_time.unwrap_or(timestamp));
+        let duration = timestamp.saturating_sub(since);
+        if self.freeze_reported || duration < Duration::from_millis(self.config.freeze_ms) {
+            return Ok(None);
+        }
+        self.freeze_reported = true;
+        Ok(Some(TamperEvent::FrozenFrame { duration }))
+    }
+
+    fn frozen_duration(&self, timestamp: Duration) -> Duration {
+        self.frozen_since.map_or(Duration::ZERO, |since| timestamp.saturating_sub(since))
+    }
+
+    /// Looks for the latest `loop_frames` hashes earlier in the history
+    fn check_loop(&mut self, hash: u64, timestamp: Duration) -> Option<TamperEvent> {
+        self.hashes.push_back((timestamp, hash));
+        while self.hashes.len() > self.config.loop_history.max(1) {
+            self.hashes.pop_front();
+        }
+
+        let length = self.config.loop_frames.max(1);
+        let tolerance = self.config.loop_max_distance;
+        let latest = self.hashes.len();
+        if latest < 2 * length {
+            return None;
+        }
+
+        // A sequence without changes is a still scene (or a freeze), not a loop
+        let changes = (latest - length + 1..latest)
+            .filter(|&i| hash_distance(self.hashes[i - 1].1, self.hashes[i].1) > tolerance)
+            .count();
+        if changes < self.config.loop_min_changes.max(1) {
+            self.in_loop = false;
+            return None;
+        }
+
+        // Earlier end positions of a matching sequence, not overlapping the latest one
+        let end = latest - 1;
+        let repeat = (length - 1..=end - length).rev().find(|&start| {
+            (0..length).all(|i| hash_distance(self.hashes[start - i].1, self.hashes[end - i].1) <= tolerance)
+        });
+
+        match repeat {
+            Some(start) if !self.in_loop => {
+                self.in_loop = true;
+                let period = timestamp.saturating_sub(self.hashes[start].0);
+                Some(TamperEvent::LoopedFootage { period })
+            }
+            Some(_) => None,
+            None => {
+                self.in_loop = false;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+// Below this read quality a payload that does not match is not trusted to
+// mean tampering, and the verdict is indeterminate instead
+pub const MIN_READ_QUALITY: f64 = 0.3;
+
+// A payload with uncorrectable errors but at most this fraction of wrong
+// symbols is treated as a misread (e.g. a briefly covered LED), not tampering
+pub const MAX_MISREAD_FRACTION: f64 = 0.25;
+
+// Confidence lost for every symbol the error-correcting code had to fix
+pub const CORRECTION_PENALTY: f64 = 0.1;
+
+/// Outcome of checking the LED code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerdictStatus {
+    Verified,      // The expected code was read
+    Unverified,    // A different code was read clearly
+    Indeterminate, // No code could be read, or too poorly to decide
+}
+
+impl VerdictStatus {
+    /// Lower-case name, used in file names
+    pub fn label(&self) -> &'static str {
+        match self {
+            VerdictStatus::Verified => "verified",
+            VerdictStatus::Unverified => "unverified",
+            VerdictStatus::Indeterminate => "indeterminate",
+        }
+    }
+
+    /// Trust ranking: Verified above Indeterminate above Unverified
+    fn rank(&self) -> u8 {
+        match self {
+            VerdictStatus::Verified => 2,
+            VerdictStatus::Indeterminate => 1,
+            VerdictStatus::Unverified => 0,
+        }
+    }
+}
+
+impl fmt::Display for VerdictStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            VerdictStatus::Verified => "VERIFIED",
+            VerdictStatus::Unverified => "TAMPER DETECTED",
+            VerdictStatus::Indeterminate => "INDETERMINATE",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Verification verdict with the confidence it is held with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Verdict {
+    pub status: VerdictStatus,
+    pub confidence: f64, // 0.0 - 1.0, confidence in `status`
+}
+
+/// What the error-correcting code did to one LED frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CorrectionReport {
+    pub corrected: usize,     // LED patterns that had one symbol fixed
+    pub uncorrectable: usize, // LED patterns with errors that could not be fixed
+}
+
+/// How well one LED frame was read, and how far it was from the expected code
+#[derive(Debug, Clone, Copy)]
+pub struct ReadQuality {
+    pub brightness_margin: f64,       // Mean distance from the nearest decision boundary (0.0 - 1.0)
+    pub timing_error: f64,            // Mean edge misalignment, as a fraction of a symbol
+    pub symbol_errors: usize,         // Symbols differing from the closest expected code after correction
+    pub symbol_count: usize,          // Payload symbols in the frame
+    pub correction: CorrectionReport, // Errors fixed or found by the error-correcting code
+}
+
+impl ReadQuality {
+    /// How cleanly the frame was read, from brightness margin and timing (0.0 - 1.0)
+    pub fn score(&self) -> f64 {
+        let timing = (1.0 - 2.0 * self.timing_error).clamp(0.0, 1.0);
+        self.brightness_margin.clamp(0.0, 1.0) * timing
+    }
+}
+
+impl Verdict {
+    /// Verdict before any LED frame has been read
+    pub fn indeterminate() -> Self {
+        Self { status: VerdictStatus::Indeterminate, confidence: 0.0 }
+    }
+
+    /// Verdict on one decoded LED frame
+    ///
+    /// A match is as trustworthy as the read was clean, less a little for
+    /// every symbol that had to be corrected. A mismatch is only called
+    /// tampering when the frame was read well, and is more certain the more
+    /// symbols differ; a few wrong symbols out of many could be misreads.
+    pub fn from_read(quality: &ReadQuality) -> Self {
+        let score = quality.score();
+
+        if quality.symbol_errors == 0 {
+            let penalty = (CORRECTION_PENALTY * quality.correction.corrected as f64).min(1.0);
+            return Self { status: VerdictStatus::Verified, confidence: score * (1.0 - penalty) };
+        }
+        if score < MIN_READ_QUALITY {
+            return Self { status: VerdictStatus::Indeterminate, confidence: 1.0 - score };
+        }
+
+        let error_fraction = quality.symbol_errors as f64 / quality.symbol_count.max(1) as f64;
+        if quality.correction.uncorrectable > 0 && error_fraction <= MAX_MISREAD_FRACTION {
+            return Self { status: VerdictStatus::Indeterminate, confidence: score };
+        }
+        Self {
+            status: VerdictStatus::Unverified,
+            confidence: score * (2.0 * error_fraction).min(1.0),
+        }
+    }
+
+    /// Whether footage with this verdict can be vouched for
+    pub fn is_verified(&self) -> bool {
+        self.status == VerdictStatus::Verified
+    }
+
+    /// The less trustworthy of two verdicts, e.g. to summarise an event
+    pub fn worst(self, other: Verdict) -> Verdict {
+        match self.status.rank().cmp(&other.status.rank()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal if self.confidence <= other.confidence => self,
+            std::cmp::Ordering::Equal => other,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (confidence {:.2})", self.status, self.confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame of 12 symbols read with the given margin and symbol errors
+    fn read(brightness_margin: f64, symbol_errors: usize, correction: CorrectionReport) -> ReadQuality {
+        ReadQuality { brightness_margin, timing_error: 0.0, symbol_errors, symbol_count: 12, correction }
+    }
+
+    fn clean() -> CorrectionReport {
+        CorrectionReport::default()
+    }
+
+    #[test]
+    fn matching_code_is_verified_with_the_read_quality_as_confidence() {
+        let verdict = Verdict::from_read(&read(0.8, 0, clean()));
+        assert_eq!(verdict.status, VerdictStatus::Verified);
+        assert!((verdict.confidence - 0.8).abs() < 1e-9);
+
+        // Even a poor read of the right code is a match, just a weak one
+        let verdict = Verdict::from_read(&read(0.1, 0, clean()));
+        assert_eq!(verdict.status, VerdictStatus::Verified);
+        assert!((verdict.confidence - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn corrected_symbols_lower_the_confidence() {
+        let corrected = CorrectionReport { corrected: 2, uncorrectable: 0 };
+        let verdict = Verdict::from_read(&read(1.0, 0, corrected));
+        assert_eq!(verdict.status, VerdictStatus::Verified);
+        assert!((verdict.confidence - (1.0 - 2.0 * CORRECTION_PENALTY)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn poor_reads_of_a_different_code_are_indeterminate() {
+        let just_below = Verdict::from_read(&read(MIN_READ_QUALITY - 0.01, 12, clean()));
+        assert_eq!(just_below.status, VerdictStatus::Indeterminate);
+        assert!((just_below.confidence - (1.0 - (MIN_READ_QUALITY - 0.01))).abs() < 1e-9);
+
+        let at_threshold = Verdict::from_read(&read(MIN_READ_QUALITY, 12, clean()));
+        assert_eq!(at_threshold.status, VerdictStatus::Unverified);
+    }
+
+    #[test]
+    fn few_uncorrectable_errors_are_misreads_not_tampering() {
+        let uncorrectable = CorrectionReport { corrected: 0, uncorrectable: 1 };
+        // 3 of 12 symbols is exactly the misread fraction
+        let misread = Verdict::from_read(&read(0.9, 3, uncorrectable));
+        assert_eq!(misread.status, VerdictStatus::Indeterminate);
+        assert!((misread.confidence - 0.9).abs() < 1e-9);
+
+        let tampered = Verdict::from_read(&read(0.9, 4, uncorrectable));
+        assert_eq!(tampered.status, VerdictStatus::Unverified);
+
+        // Without the code reporting errors, any difference is a wrong code
+        let wrong = Verdict::from_read(&read(0.9, 1, clean()));
+        assert_eq!(wrong.status, VerdictStatus::Unverified);
+    }
+
+    #[test]
+    fn wrong_code_confidence_grows_with_the_errors() {
+        let confidence = |errors| Verdict::from_read(&read(1.0, errors, clean())).confidence;
+        assert!((confidence(3) - 0.5).abs() < 1e-9);
+        assert!((confidence(6) - 1.0).abs() < 1e-9);
+        assert!((confidence(12) - 1.0).abs() < 1e-9);
+        assert!(confidence(1) < confidence(2));
+    }
+
+    #[test]
+    fn timing_error_lowers_the_read_quality() {
+        let mut quality = read(1.0, 0, clean());
+        quality.timing_error = 0.25;
+        assert!((quality.score() - 0.5).abs() < 1e-9);
+        quality.timing_error = 0.5;
+        assert_eq!(Verdict::from_read(&quality).confidence, 0.0);
+    }
+
+    #[test]
+    fn worst_prefers_unverified_then_indeterminate_then_lower_confidence() {
+        let verified = Verdict { status: VerdictStatus::Verified, confidence: 0.9 };
+        let weak = Verdict { status: VerdictStatus::Verified, confidence: 0.4 };
+        let unverified = Verdict { status: VerdictStatus::Unverified, confidence: 0.9 };
+        assert_eq!(verified.worst(Verdict::indeterminate()).status, VerdictStatus::Indeterminate);
+        assert_eq!(Verdict::indeterminate().worst(unverified), unverified);
+        assert_eq!(verified.worst(weak), weak);
+    }
+}
@@ -0,0 +1,609 @@
+use opencv::{
+    core::{self, Mat, Rect},
+    prelude::*,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::controller::camera::classifier::{ClassifierConfig, LedClassifier};
+use crate::controller::camera::decoder::{DecodedFrame, SymbolDecoder};
+use crate::controller::camera::policy::{StateTransition, VerificationPolicy, VerificationState, VerificationStateMachine};
+use crate::controller::camera::verdict::{CorrectionReport, ReadQuality, Verdict, VerdictStatus};
+use crate::controller::security::ecc::{Correction, SymbolCode};
+use crate::controller::security::protocol::{self, SYMBOL_DURATION};
+use crate::controller::security::rolling_code::RollingCode;
+use crate::controller::security::secure_pattern_generator::{derive_led_patterns, PatternConfig};
+
+pub const SAMPLE_INTERVAL: Duration = SYMBOL_DURATION; // Calibration samples once per LED symbol
+pub const CALIBRATION_SAMPLES: usize = 10; // Number of samples for calibration
+pub const MIN_BRIGHTNESS_DIFF: f64 = 2.0; // Minimum difference between max and min brightness
+pub const VERIFICATION_HOLD_DURATION: Duration = Duration::from_millis(3500); // Default grace period for the next LED frame
+
+/// Regions of the frame covering the status LEDs, one per LED channel
+#[derive(Debug, Clone)]
+pub struct LedRois {
+    pub rects: Vec<Rect>, // In the order of the configured LED channels
+}
+
+impl LedRois {
+    /// Places the LED regions in the top-right corner of the frame
+    ///
+    /// The main ROI covers 10% of the frame size with a 10-pixel margin and is
+    /// split into `count` equal columns, left to right in channel order.
+    ///
+    /// # Arguments
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    /// * `count` - Number of LEDs
+    pub fn top_right(width: i32, height: i32, count: usize) -> Self {
+        let rect_width = width as f32 * 0.1; // 10% of frame width
+        let rect_height = height as f32 * 0.1; // 10% of frame height
+        let top_left_x = width as f32 - rect_width - 10.0; // 10-pixel margin from right
+        let top_left_y = 10.0; // 10-pixel margin from top
+        let column_width = rect_width / count.max(1) as f32;
+
+        let rects = (0..count)
+            .map(|i| {
+                Rect::new(
+                    (top_left_x + column_width * i as f32) as i32,
+                    top_left_y as i32,
+                    column_width as i32,
+                    rect_height as i32,
+                )
+            })
+            .collect();
+
+        Self { rects }
+    }
+
+    /// Number of LED regions
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    /// Whether there are no LED regions
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+}
+
+/// A fixed payload, one pattern per LED
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedPatterns {
+    pub leds: Vec<Vec<u8>>,             // Pattern of levels per LED channel, parity included
+    pub levels: u8,                     // Brightness levels per symbol
+    pub error_code: Option<SymbolCode>, // Error-correcting code of each pattern
+}
+
+impl ExpectedPatterns {
+    /// Derives the patterns from the secret shared with the LED controller
+    ///
+    /// # Arguments
+    /// * `config` - Pattern parameters, identical to the controller's
+    /// * `secret` - Shared secret
+    pub fn from_secret(config: &PatternConfig, secret: &[u8]) -> Result<Self> {
+        Ok(Self {
+            leds: derive_led_patterns(config, secret)?,
+            levels: config.levels,
+            error_code: config.error_code(),
+        })
+    }
+
+    /// Number of payload symbols
+    pub fn len(&self) -> usize {
+        self.leds.first().map_or(0, Vec::len)
+    }
+
+    /// Whether the patterns are empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Calibrated brightness of one LED when off and when fully lit
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LedRange {
+    pub off: f64,
+    pub on: f64,
+    #[serde(default)]
+    pub noise: f64, // Standard deviation of the brightness around either level
+}
+
+impl LedRange {
+    /// Brightness separating OFF from any lit level
+    pub fn threshold(&self) -> f64 {
+        (self.on + self.off) / 2.0
+    }
+
+    /// Difference between the fully lit and the off brightness
+    pub fn contrast(&self) -> f64 {
+        self.on - self.off
+    }
+}
+
+/// Calibrated brightness ranges of every LED
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thresholds {
+    pub leds: Vec<LedRange>, // In the order of the configured LED channels
+}
+
+/// Computes the average brightness of a region
+///
+/// # Arguments
+/// * `frame` - The frame to sample
+/// * `roi` - Region to average over
+///
+/// # Returns
+/// * `Result<f64>` - Mean of the first channel (BGR) inside the region
+pub fn roi_brightness(frame: &Mat, roi: Rect) -> Result<f64> {
+    let region = Mat::roi(frame, roi)?;
+    Ok(core::mean(&region, &Mat::default())?.0[0])
+}
+
+/// Collects brightness samples and derives each LED's OFF/ON range
+pub struct Calibration {
+    rois: LedRois,
+    classifier: ClassifierConfig, // How brightness is measured
+    brightnesses: Vec<Vec<f64>>,  // Samples per LED
+}
+
+impl Calibration {
+    /// Creates an empty calibration session for the given LED regions
+    pub fn new(rois: LedRois) -> Self {
+        let brightnesses = vec![Vec::with_capacity(CALIBRATION_SAMPLES); rois.len()];
+        Self { rois, classifier: ClassifierConfig::default(), brightnesses }
+    }
+
+    /// Measures brightness as configured (colour channels) instead of on the blue channel
+    pub fn with_classifier(mut self, classifier: ClassifierConfig) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Records the LED brightnesses of one frame
+    ///
+    /// # Returns
+    /// * `Result<Vec<f64>>` - Measured brightness of each LED
+    pub fn add_sample(&mut self, frame: &Mat) -> Result<Vec<f64>> {
+        let sample = self.classifier.measure(frame, &self.rois)?;
+
+        for (samples, &brightness) in self.brightnesses.iter_mut().zip(&sample) {
+            samples.push(brightness);
+        }
+
+        Ok(sample)
+    }
+
+    /// Regions being calibrated
+    pub fn rois(&self) -> &LedRois {
+        &self.rois
+    }
+
+    /// Number of samples collected in the current attempt
+    pub fn sample_count(&self) -> usize {
+        self.brightnesses.first().map_or(0, Vec::len)
+    }
+
+    /// Whether enough samples have been collected to attempt `finish`
+    pub fn is_complete(&self) -> bool {
+        self.sample_count() >= CALIBRATION_SAMPLES
+    }
+
+    /// Derives the brightness ranges from the collected samples
+    ///
+    /// The samples are cleared either way, so a failed attempt can simply be retried.
+    ///
+    /// # Returns
+    /// * `Option<Thresholds>` - Ranges, or None if the contrast of any LED was too low
+    pub fn finish(&mut self) -> Option<Thresholds> {
+        let mut leds = Vec::with_capacity(self.brightnesses.len());
+        let mut success = true;
+
+        // Calculate max, min, and threshold for each LED
+        for (i, samples) in self.brightnesses.iter_mut().enumerate() {
+            let max = samples.iter().fold(f64::MIN, |a, &b| a.max(b));
+            let min = samples.iter().fold(f64::MAX, |a, &b| a.min(b));
+
+            // Spread of the samples around the off and lit levels
+            let midpoint = (max + min) / 2.0;
+            let (lit, dark): (Vec<f64>, Vec<f64>) = samples.iter().partition(|&&b| b > midpoint);
+            let noise = std_dev(&lit).max(std_dev(&dark));
+            samples.clear();
+
+            let diff = max - min;
+            println!(
+                "LED{}: Max = {:.2}, Min = {:.2}, Diff = {:.2}, Noise = {:.2}",
+                i + 1, max, min, diff, noise
+            );
+
+            // Check if the difference meets the minimum requirement
+            success &= diff >= MIN_BRIGHTNESS_DIFF;
+            leds.push(LedRange { off: min, on: max, noise });
+        }
+
+        if success {
+            let summary: Vec<String> = leds.iter().enumerate()
+                .map(|(i, range)| format!("LED{} Threshold = {:.2}", i + 1, range.threshold()))
+                .collect();
+            println!("Calibration successful: {}", summary.join(", "));
+            Some(Thresholds { leds })
+        } else {
+            println!("Calibration failed: not every LED changed by {:.2}. Retrying...", MIN_BRIGHTNESS_DIFF);
+            None
+        }
+    }
+}
+
+/// Standard deviation of a set of samples (0 for fewer than two)
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+/// Tracks the brightness range each LED actually shows
+///
+/// Every LED frame starts with a preamble at full brightness followed by an
+/// off symbol, so within one frame period each LED passes through both
+/// extremes and the spread of its brightness is its current contrast.
+struct ContrastMonitor {
+    window: Duration,                          // Span the contrast is measured over
+    samples: VecDeque<(Duration, Vec<f64>)>,   // Brightness of every LED per frame
+    started: Option<Duration>,                 // Timestamp of the first sample
+}
+
+impl ContrastMonitor {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+            started: None,
+        }
+    }
+
+    fn push(&mut self, timestamp: Duration, brightnesses: Vec<f64>) {
+        self.started.get_or_insert(timestamp);
+        self.samples.push_back((timestamp, brightnesses));
+        while self.samples.front().is_some_and(|(t, _)| timestamp.saturating_sub(*t) > self.window) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Brightness spread of every LED, once a whole window has been seen
+    fn contrast(&self) -> Option<Vec<f64>> {
+        let (latest, first) = (self.samples.back()?.0, self.started?);
+        if latest.saturating_sub(first) < self.window {
+            return None;
+        }
+
+        let led_count = self.samples.back()?.1.len();
+        Some((0..led_count)
+            .map(|led| {
+                let values = self.samples.iter().map(|(_, b)| b[led]);
+                values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+            })
+            .collect())
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.started = None;
+    }
+}
+
+/// What the verifier expects the LEDs to send
+#[derive(Debug, Clone)]
+pub enum ExpectedCode {
+    /// The same payload in every frame
+    Fixed(ExpectedPatterns),
+    /// Time-based rolling code, accepted within `max_skew` of the camera clock
+    Rolling { code: RollingCode, max_skew: Duration },
+}
+
+impl ExpectedCode {
+    /// Number of payload symbols per frame
+    pub fn payload_length(&self) -> usize {
+        match self {
+            ExpectedCode::Fixed(patterns) => patterns.len(),
+            ExpectedCode::Rolling { code, .. } => code.payload_length(),
+        }
+    }
+
+    /// Brightness levels per symbol
+    pub fn levels(&self) -> u8 {
+        match self {
+            ExpectedCode::Fixed(patterns) => patterns.levels,
+            ExpectedCode::Rolling { code, .. } => code.levels(),
+        }
+    }
+
+    /// Number of LEDs the code is sent on
+    pub fn led_count(&self) -> usize {
+        match self {
+            ExpectedCode::Fixed(patterns) => patterns.leds.len(),
+            ExpectedCode::Rolling { code, .. } => code.led_count(),
+        }
+    }
+
+    /// Time between the starts of two consecutive frames
+    pub fn frame_period(&self) -> Duration {
+        SYMBOL_DURATION * protocol::frame_length(self.payload_length()) as u32
+    }
+
+    /// Error-correcting code of each LED pattern, if enabled
+    pub fn error_code(&self) -> Option<SymbolCode> {
+        match self {
+            ExpectedCode::Fixed(patterns) => patterns.error_code.clone(),
+            ExpectedCode::Rolling { code, .. } => code.error_code(),
+        }
+    }
+
+    /// Applies the error-correcting code to every LED pattern of a decoded frame
+    ///
+    /// # Returns
+    /// * `(DecodedFrame, CorrectionReport)` - The frame with corrected payload
+    ///   (unchanged without a code) and how many patterns were fixed or not
+    pub fn correct(&self, frame: &DecodedFrame) -> (DecodedFrame, CorrectionReport) {
+        let mut corrected = frame.clone();
+        let mut report = CorrectionReport::default();
+
+        if let Some(code) = self.error_code() {
+            for pattern in corrected.payload.iter_mut() {
+                let (fixed, correction) = code.decode(pattern);
+                match correction {
+                    Correction::Clean => {}
+                    Correction::Corrected { .. } => report.corrected += 1,
+                    Correction::Uncorrectable => report.uncorrectable += 1,
+                }
+                *pattern = fixed;
+            }
+        }
+        (corrected, report)
+    }
+
+    /// Levels the LEDs show at `time`, preamble included
+    ///
+    /// A fixed payload has no absolute phase; its frames are taken to start at
+    /// multiples of the frame period since the epoch.
+    pub fn symbols_at(&self, time: SystemTime) -> Result<Vec<u8>> {
+        match self {
+            ExpectedCode::Fixed(patterns) => {
+                let millis = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                let symbol = millis / SYMBOL_DURATION.as_millis();
+                let index = (symbol % protocol::frame_length(patterns.len()) as u128) as usize;
+                Ok(protocol::frame_symbol(&patterns.leds, index, patterns.levels))
+            }
+            ExpectedCode::Rolling { code, .. } => code.symbols_at(time),
+        }
+    }
+
+    /// Whether a decoded frame carries the expected payload
+    ///
+    /// A rolling code is only accepted from a window starting within
+    /// `max_skew` of when the frame was seen, so replayed footage fails.
+    pub fn accepts(&self, frame: &DecodedFrame) -> bool {
+        self.symbol_errors(frame) == Some(0)
+    }
+
+    /// Number of payload symbols differing from the closest expected payload
+    ///
+    /// # Returns
+    /// * `Option<usize>` - Differing symbols over all LEDs, or None if the
+    ///   frame does not have the expected shape or no window is within skew
+    ///   (windows without a usable code, which the controller cannot send
+    ///   either, never match)
+    pub fn symbol_errors(&self, frame: &DecodedFrame) -> Option<usize> {
+        match self {
+            ExpectedCode::Fixed(patterns) => payload_distance(&patterns.leds, &frame.payload),
+            ExpectedCode::Rolling { code, max_skew } => {
+                let window = code.window_at(frame.start_time + SYMBOL_DURATION / 2);
+                (window.saturating_sub(1)..=window + 1)
+                    .filter(|&candidate| {
+                        let start = code.window_start(candidate);
+                        let skew = start.duration_since(frame.start_time)
+                            .or_else(|_| frame.start_time.duration_since(start))
+                            .unwrap_or_default();
+                        skew <= *max_skew
+                    })
+                    .filter_map(|candidate| payload_distance(&code.code_for_window(candidate).ok()?, &frame.payload))
+                    .min()
+            }
+        }
+    }
+}
+
+/// Number of differing symbols between two payloads of the same shape
+fn payload_distance(expected: &[Vec<u8>], actual: &[Vec<u8>]) -> Option<usize> {
+    if expected.len() != actual.len() || expected.iter().zip(actual).any(|(e, a)| e.len() != a.len()) {
+        return None;
+    }
+    Some(expected.iter()
+        .zip(actual)
+        .map(|(e, a)| e.iter().zip(a).filter(|(x, y)| x != y).count())
+        .sum())
+}
+
+/// Verifies the LED code visible in the camera feed
+pub struct LedVerifier {
+    rois: LedRois,
+    thresholds: Thresholds,
+    classifier_config: ClassifierConfig, // How brightness is measured and classified
+    classifiers: Vec<LedClassifier>,     // Level classifier per LED
+    expected: ExpectedCode,              // What the LED controller sends
+    decoder: SymbolDecoder,              // Recovers LED frames from per-frame states
+    contrast: ContrastMonitor,           // Measured LED contrast, to detect drift
+    verdict: Verdict,                    // Current verification verdict
+    state: VerificationStateMachine,     // Verification state over successive LED frames
+    margin_sum: f64,                     // Brightness margins since the last decoded frame
+    margin_count: usize,
+    last_read: Option<ReadQuality>,      // Quality of the last decoded LED frame
+}
+
+impl LedVerifier {
+    /// Creates a new LedVerifier instance
+    ///
+    /// # Arguments
+    /// * `rois` - Regions covering the LEDs, in channel order
+    /// * `thresholds` - Calibrated brightness range of each LED
+    /// * `expected` - What the LED controller sends
+    pub fn new(rois: LedRois, thresholds: Thresholds, expected: ExpectedCode) -> Self {
+        let classifier_config = ClassifierConfig::default();
+        Self {
+            rois,
+            classifiers: classifiers(&thresholds, expected.levels(), &classifier_config),
+            thresholds,
+            classifier_config,
+            decoder: SymbolDecoder::new(expected.payload_length(), expected.levels()),
+            contrast: ContrastMonitor::new(expected.frame_period() + SYMBOL_DURATION),
+            expected,
+            verdict: Verdict::indeterminate(),
+            state: VerificationStateMachine::new(VerificationPolicy::default()),
+            margin_sum: 0.0,
+            margin_count: 0,
+            last_read: None,
+        }
+    }
+
+    /// Uses the given hysteresis, baseline tracking and LED colours
+    pub fn with_classifier(mut self, config: ClassifierConfig) -> Self {
+        self.classifiers = classifiers(&self.thresholds, self.expected.levels(), &config);
+        self.classifier_config = config;
+        self
+    }
+
+    /// Uses the given grace periods and tamper thresholds
+    pub fn with_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.state.set_policy(policy);
+        self
+    }
+
+    /// Registers a callback fired on every verification state transition
+    pub fn on_transition(&mut self, hook: impl FnMut(&StateTransition) + Send + 'static) {
+        self.state.on_transition(hook);
+    }
+
+    /// Feeds one frame to the verifier
+    ///
+    /// Every frame is classified and passed to the decoder. The verdict changes
+    /// when a complete LED frame has been decoded, and becomes indeterminate if
+    /// no LED frame follows within one frame period plus the policy's hold
+    /// time, or none is decoded at all after the first frame.
+    ///
+    /// Its confidence combines how clearly the brightness fell on a level, how
+    /// well the symbol edges lined up and how many symbols differed from the
+    /// expected code. Every decoded or missed frame is a window of the
+    /// verification state machine.
+    ///
+    /// # Arguments
+    /// * `frame` - The latest camera frame
+    /// * `timestamp` - Capture time of the frame on the source's clock
+    /// * `capture_time` - Wall-clock capture time, used to check rolling codes
+    ///
+    /// # Returns
+    /// * `Result<Verdict>` - Current verification verdict
+    pub fn process_frame(&mut self, frame: &Mat, timestamp: Duration, capture_time: SystemTime) -> Result<Verdict> {
+        // Quantise the average brightness of each LED region to a symbol level
+        let brightnesses = self.classifier_config.measure(frame, &self.rois)?;
+        let mut states = Vec::with_capacity(brightnesses.len());
+        for (classifier, &brightness) in self.classifiers.iter_mut().zip(&brightnesses) {
+            states.push(classifier.classify(brightness));
+            self.margin_sum += classifier.margin(brightness);
+            self.margin_count += 1;
+        }
+        self.contrast.push(timestamp, brightnesses);
+
+        // Checked first, so frames dropped for a whole window miss it even
+        // when the frame after the gap completes an LED frame
+        let missed = self.state.check_missed(timestamp, self.expected.frame_period());
+
+        if let Some(decoded) = self.decoder.push(timestamp, capture_time, &states) {
+            let (corrected, correction) = self.expected.correct(&decoded);
+            let symbol_count = decoded.payload.iter().map(Vec::len).sum();
+            let quality = ReadQuality {
+                brightness_margin: self.margin_sum / self.margin_count.max(1) as f64,
+                timing_error: decoded.timing_error,
+                symbol_errors: self.expected.symbol_errors(&corrected).unwrap_or(symbol_count),
+                symbol_count,
+                correction,
+            };
+            self.last_read = Some(quality);
+            self.verdict = Verdict::from_read(&quality);
+            self.margin_sum = 0.0;
+            self.margin_count = 0;
+            self.state.record_window(timestamp, self.verdict);
+        } else if missed {
+            // No LED frame arrived in time, so nothing can be said about the footage
+            self.verdict = Verdict::indeterminate();
+        }
+
+        Ok(self.verdict)
+    }
+
+    /// Current verification verdict
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    /// Current verification state
+    pub fn state(&self) -> VerificationState {
+        self.state.state()
+    }
+
+    /// How the last LED frame was read, including corrected and uncorrectable errors
+    pub fn last_read(&self) -> Option<ReadQuality> {
+        self.last_read
+    }
+
+    /// Whether the current verdict is Verified
+    pub fn is_verified(&self) -> bool {
+        self.verdict.status == VerdictStatus::Verified
+    }
+
+    /// Whether the decoder is locked onto an LED frame
+    pub fn is_locked(&self) -> bool {
+        self.decoder.is_locked()
+    }
+
+    /// Regions covering the LEDs
+    pub fn rois(&self) -> &LedRois {
+        &self.rois
+    }
+
+    /// Calibrated brightness ranges in use
+    pub fn thresholds(&self) -> &Thresholds {
+        &self.thresholds
+    }
+
+    /// How brightness is measured and classified
+    pub fn classifier_config(&self) -> &ClassifierConfig {
+        &self.classifier_config
+    }
+
+    /// Replaces the brightness ranges, e.g. after a recalibration
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.classifiers = classifiers(&thresholds, self.expected.levels(), &self.classifier_config);
+        self.thresholds = thresholds;
+        self.contrast.clear();
+    }
+
+    /// How far the measured LED contrast has moved from the calibrated one
+    ///
+    /// # Returns
+    /// * `Option<f64>` - Largest relative change over all LEDs (0.5 = 50%), or
+    ///   None until a whole LED frame has been measured
+    pub fn contrast_drift(&self) -> Option<f64> {
+        let measured = self.contrast.contrast()?;
+        Some(measured.iter()
+            .zip(&self.thresholds.leds)
+            .map(|(&contrast, range)| (contrast / range.contrast().max(f64::EPSILON) - 1.0).abs())
+            .fold(0.0, f64::max))
+    }
+}
+
+/// One level classifier per calibrated LED
+fn classifiers(thresholds: &Thresholds, levels: u8, config: &ClassifierConfig) -> Vec<LedClassifier> {
+    thresholds.leds.iter().map(|range| LedClassifier::new(range, levels, config)).collect()
+}
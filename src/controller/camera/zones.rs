@@ -0,0 +1,251 @@
+use opencv::{
+    core::{self, Mat, Point, Point2f, Rect, Scalar, Size, Vector},
+    imgproc,
+    prelude::*,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use crate::controller::camera::verifier::LedRois;
+
+// Name reported for motion when no include zone is configured
+pub const WHOLE_FRAME_ZONE: &str = "frame";
+
+/// Whether a zone is watched for motion or ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneKind {
+    #[default]
+    Include, // Motion inside the polygon is reported
+    Exclude, // Motion inside the polygon is ignored, e.g. trees or a TV
+}
+
+/// A polygon of the frame with its own motion settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneConfig {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ZoneKind,
+    pub points: Vec<[i32; 2]>, // Polygon corners in frame pixels
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f64,      // Scales the motion area measured in the zone (0.5 = half as sensitive)
+    pub min_area: Option<f64>, // Smallest motion area in the zone, the detector's minimum if not given
+}
+
+fn default_sensitivity() -> f64 {
+    1.0
+}
+
+/// Where in the frame motion is looked for
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MotionConfig {
+    pub exclude_led_rois: bool, // Ignore the blinking LEDs
+    pub zones: Vec<ZoneConfig>, // Include and exclude zones; without include zones the whole frame is watched
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            exclude_led_rois: true,
+            zones: Vec::new(),
+        }
+    }
+}
+
+/// One blob of motion
+#[derive(Debug, Clone)]
+pub struct MotionRegion {
+    pub bbox: Rect,       // Bounding box in frame pixels
+    pub area: f64,        // Contour area in pixels, before the zone sensitivity is applied
+    pub centroid: Point2f,
+    pub zone: String,     // Zone the region was found in
+}
+
+/// Outcome of motion detection on one frame
+#[derive(Debug, Clone, Default)]
+pub struct MotionResult {
+    pub regions: Vec<MotionRegion>, // Regions larger than the minimum area of their zone, largest first
+    pub centroid: Option<Point2f>,  // Area-weighted centre of the regions
+    pub changed_fraction: f64,      // Fraction of the frame that is foreground inside the zones
+    pub mask: Mat,                  // Foreground inside the zones (255 = foreground), empty before the background is known
+    pub zone: Option<String>,       // Zone of the largest region
+}
+
+impl MotionResult {
+    /// Whether any region was large enough to count as motion
+    pub fn detected(&self) -> bool {
+        !self.regions.is_empty()
+    }
+
+    /// Bounding box of all the regions
+    pub fn bounds(&self) -> Option<Rect> {
+        self.regions.iter().map(|region| region.bbox).reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            let right = (a.x + a.width).max(b.x + b.width);
+            let bottom = (a.y + a.height).max(b.y + b.height);
+            Rect::new(x, y, right - x, bottom - y)
+        })
+    }
+
+    /// Sum of the region areas
+    pub fn total_area(&self) -> f64 {
+        self.regions.iter().map(|region| region.area).sum()
+    }
+}
+
+/// Mask of one include zone, exclusions already removed
+struct ZoneMask {
+    name: String,
+    mask: Mat,
+    sensitivity: f64,
+    min_area: Option<f64>,
+}
+
+/// Applies the motion zones to foreground masks
+///
+/// Each include zone (or the whole frame when there are none) becomes a mask
+/// with every exclude zone and the LED regions cut out. Every foreground blob
+/// in a zone larger than the zone's minimum area is a motion region.
+pub struct MotionZones {
+    config: MotionConfig,
+    led_rois: Vec<core::Rect>, // LED regions to exclude
+    masks: Vec<ZoneMask>,      // Built for `size` on first use
+    size: Size,
+}
+
+impl MotionZones {
+    /// Creates the zones from their configuration
+    pub fn new(config: MotionConfig) -> Self {
+        Self {
+            config,
+            led_rois: Vec::new(),
+            masks: Vec::new(),
+            size: Size::default(),
+        }
+    }
+
+    /// Sets the LED regions, excluded if `exclude_led_rois` is set
+    ///
+    /// The masks are only rebuilt if the regions changed, so this can be
+    /// called on every frame.
+    pub fn set_led_rois(&mut self, rois: &LedRois) {
+        if self.config.exclude_led_rois && self.led_rois != rois.rects {
+            self.led_rois = rois.rects.clone();
+            self.masks.clear();
+        }
+    }
+
+    /// Finds the motion regions of a foreground mask inside the zones
+    ///
+    /// # Arguments
+    /// * `foreground` - Binary foreground mask (255 = foreground)
+    /// * `min_area` - Smallest motion area for zones without their own minimum
+    ///
+    /// # Returns
+    /// * `Result<MotionResult>` - Regions, centroid, changed fraction and mask
+    pub fn detect(&mut self, foreground: &Mat, min_area: f64) -> Result<MotionResult> {
+        let size = foreground.size()?;
+        if self.masks.is_empty() || self.size != size {
+            self.build_masks(size)?;
+        }
+
+        let mut mask = Mat::new_size_with_default(size, core::CV_8UC1, Scalar::all(0.0))?;
+        let mut regions = Vec::new();
+        for zone in &self.masks {
+            let mut masked = Mat::default();
+            core::bitwise_and(foreground, &zone.mask, &mut masked, &Mat::default())?;
+
+            let mut contours = Vector::<Vector<Point>>::new();
+            imgproc::find_contours(
+                &masked,
+                &mut contours,
+                imgproc::RETR_EXTERNAL,
+                imgproc::CHAIN_APPROX_SIMPLE,
+                Point::default(),
+            )?;
+
+            let zone_min_area = zone.min_area.unwrap_or(min_area);
+            for contour in contours.iter() {
+                let area = imgproc::contour_area(&contour, false)?;
+                if area * zone.sensitivity <= zone_min_area {
+                    continue;
+                }
+                let bbox = imgproc::bounding_rect(&contour)?;
+                let moments = imgproc::moments(&contour, false)?;
+                let centroid = if moments.m00 > 0.0 {
+                    Point2f::new((moments.m10 / moments.m00) as f32, (moments.m01 / moments.m00) as f32)
+                } else {
+                    Point2f::new(bbox.x as f32 + bbox.width as f32 / 2.0, bbox.y as f32 + bbox.height as f32 / 2.0)
+                };
+                regions.push(MotionRegion { bbox, area, centroid, zone: zone.name.clone() });
+            }
+
+            let mut combined = Mat::default();
+            core::bitwise_or(&mask, &masked, &mut combined, &Mat::default())?;
+            mask = combined;
+        }
+        regions.sort_by(|a, b| b.area.total_cmp(&a.area));
+
+        let total_area: f64 = regions.iter().map(|region| region.area).sum();
+        let centroid = (total_area > 0.0).then(|| {
+            let (x, y) = regions.iter().fold((0.0, 0.0), |(x, y), region| {
+                (x + region.centroid.x as f64 * region.area, y + region.centroid.y as f64 * region.area)
+            });
+            Point2f::new((x / total_area) as f32, (y / total_area) as f32)
+        });
+        let pixels = (size.width as f64 * size.height as f64).max(1.0);
+
+        Ok(MotionResult {
+            zone: regions.first().map(|region| region.zone.clone()),
+            centroid,
+            changed_fraction: core::count_non_zero(&mask)? as f64 / pixels,
+            regions,
+            mask,
+        })
+    }
+
+    fn build_masks(&mut self, size: Size) -> Result<()> {
+        // Everything to cut out of the include zones
+        let mut excluded = Mat::new_size_with_default(size, core::CV_8UC1, Scalar::all(255.0))?;
+        for zone in self.config.zones.iter().filter(|zone| zone.kind == ZoneKind::Exclude) {
+            imgproc::fill_poly_def(&mut excluded, &polygon(zone), Scalar::all(0.0))?;
+        }
+        for &roi in &self.led_rois {
+            imgproc::rectangle(&mut excluded, roi, Scalar::all(0.0), imgproc::FILLED, imgproc::LINE_8, 0)?;
+        }
+
+        let includes: Vec<&ZoneConfig> = self.config.zones.iter().filter(|zone| zone.kind == ZoneKind::Include).collect();
+        self.masks = if includes.is_empty() {
+            vec![ZoneMask {
+                name: WHOLE_FRAME_ZONE.to_string(),
+                mask: excluded,
+                sensitivity: 1.0,
+                min_area: None,
+            }]
+        } else {
+            let mut masks = Vec::with_capacity(includes.len());
+            for zone in includes {
+                let mut area = Mat::new_size_with_default(size, core::CV_8UC1, Scalar::all(0.0))?;
+                imgproc::fill_poly_def(&mut area, &polygon(zone), Scalar::all(255.0))?;
+                let mut mask = Mat::default();
+                core::bitwise_and(&area, &excluded, &mut mask, &Mat::default())?;
+                masks.push(ZoneMask {
+                    name: zone.name.clone(),
+                    mask,
+                    sensitivity: zone.sensitivity.max(0.0),
+                    min_area: zone.min_area,
+                });
+            }
+            masks
+        };
+        self.size = size;
+        Ok(())
+    }
+}
+
+/// Corners of a zone in the form `fill_poly` takes
+fn polygon(zone: &ZoneConfig) -> Vector<Vector<Point>> {
+    let points: Vector<Point> = zone.points.iter().map(|&[x, y]| Point::new(x, y)).collect();
+    Vector::from_iter([points])
+}
@@ -1,26 +1,37 @@
-// Output security patternuse std::fs::OpenOptions;
+// Output security pattern
+use std::fs::OpenOptions;
 use std::io::{self, Write};
+use std::path::Path;
 use std::thread;
-use std::time::Duration;
-use std::fs::OpenOptions;
+use std::time::SystemTime;
+use rust_security_camera::controller::security::rolling_code::RollingCode;
+use rust_security_camera::controller::security::secure_pattern_generator::{PatternConfig, DEFAULT_CONFIG_PATH};
+use rust_security_camera::controller::security::shared_secret::{self, DEFAULT_SECRET_PATH};
 
-fn main() -> io::Result<()> {
-    let act_path = "/sys/class/leds/ACT/brightness";
-    let pwr_path = "/sys/class/leds/PWR/brightness";
+fn main() -> anyhow::Result<()> {
+    // First run: create the secret that the camera has to be given as well
+    if !Path::new(DEFAULT_SECRET_PATH).exists() && std::env::var(shared_secret::SECRET_ENV_VAR).is_err() {
+        shared_secret::save_secret(DEFAULT_SECRET_PATH, &shared_secret::generate_secret())?;
+        println!("Generated new shared secret in {}, copy it to the camera host", DEFAULT_SECRET_PATH);
+    }
 
-    let led1_pattern: [u8; 4] = [0, 0, 1, 0];
-    let led2_pattern: [u8; 4] = [0, 1, 1, 0];
+    let config = PatternConfig::load(DEFAULT_CONFIG_PATH)?;
+    let secret = shared_secret::load_secret(DEFAULT_SECRET_PATH)?;
+    let code = RollingCode::new(&secret, &config);
 
+    // Show the code of the current time window, switching exactly on symbol
+    // boundaries of the system clock so the camera can follow along
     loop {
-        for i in 0..led1_pattern.len() {
-            write_to_led(act_path, led1_pattern[i])?;
-            write_to_led(pwr_path, led2_pattern[i])?;
-            thread::sleep(Duration::from_secs(1));
+        let now = SystemTime::now();
+        let levels = code.symbols_at(now)?;
+        for (led, &level) in config.leds.iter().zip(&levels) {
+            write_to_led(&led.path, led.brightness(level, config.levels))?;
         }
+        thread::sleep(code.time_to_next_symbol(SystemTime::now()));
     }
 }
 
-fn write_to_led(path: &str, value: u8) -> io::Result<()> {
+fn write_to_led(path: &str, value: u32) -> io::Result<()> {
     let mut file = OpenOptions::new()
         .write(true)
         .open(path)
@@ -33,4 +44,4 @@ fn write_to_led(path: &str, value: u8) -> io::Result<()> {
         .expect("Failed to flush changes to LED brightness file");
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,2 +1,4 @@
 // Controller module declarations
-pub mod camera;
\ No newline at end of file
+#[cfg(feature = "camera")]
+pub mod camera;
+pub mod security;
\ No newline at end of file
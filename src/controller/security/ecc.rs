@@ -0,0 +1,194 @@
+// Error-correcting code for LED patterns
+//
+// A Hamming-style code over the symbol alphabet 0..levels. Each LED pattern is
+// sent as its data symbols followed by parity symbols; the camera can then fix
+// a single misread symbol per LED and detects (without fixing) two, e.g. an
+// LED briefly covered for a couple of symbols.
+
+/// Outcome of decoding one received pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    Clean,                       // No error found
+    Corrected { position: usize }, // One symbol was wrong and has been fixed
+    Uncorrectable,               // Errors were detected that cannot be fixed
+}
+
+/// Single-error-correcting code over `levels`-ary symbols
+///
+/// Every column of the parity check matrix is `(1, b)` with `b` a vector of
+/// bits: parity symbols get the zero vector and the unit vectors, data symbols
+/// the bit vectors with at least two ones. The leading 1 is an overall parity,
+/// so a single error of size `e` gives a syndrome `(e, e * b)`; since `b` only
+/// holds 0s and 1s this identifies the column for any number of levels, prime
+/// or not. Two errors never look like one, so they are always detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolCode {
+    data_length: usize,
+    levels: u8,
+    columns: Vec<Vec<u8>>, // Check column of every symbol, data then parity
+}
+
+impl SymbolCode {
+    /// Creates a code for patterns of `data_length` symbols with `levels` levels
+    pub fn new(data_length: usize, levels: u8) -> Self {
+        // Enough bits for a distinct vector with two or more ones per data symbol
+        let mut bits = 2;
+        while (1usize << bits) - bits - 1 < data_length {
+            bits += 1;
+        }
+
+        let column = |vector: usize| -> Vec<u8> {
+            std::iter::once(1).chain((0..bits).map(|bit| ((vector >> bit) & 1) as u8)).collect()
+        };
+        let data = (3usize..).filter(|v| v.count_ones() >= 2).take(data_length).map(column);
+        let parity = std::iter::once(0).chain((0..bits).map(|bit| 1 << bit)).map(column);
+
+        Self {
+            data_length,
+            levels: levels.max(2),
+            columns: data.chain(parity).collect(),
+        }
+    }
+
+    /// Number of parity symbols appended to each pattern
+    pub fn parity_length(&self) -> usize {
+        self.columns.len() - self.data_length
+    }
+
+    /// Length of an encoded pattern
+    pub fn encoded_length(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Appends the parity symbols to a data pattern
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let q = self.levels as u32;
+        let rows = self.parity_length();
+
+        // Data part of the syndrome
+        let mut sums = vec![0u32; rows];
+        for (column, &symbol) in self.columns.iter().zip(data) {
+            for (sum, &c) in sums.iter_mut().zip(column) {
+                *sum = (*sum + c as u32 * symbol as u32) % q;
+            }
+        }
+
+        // Parity symbol i (i >= 1) alone cancels row i; parity 0 then
+        // cancels the overall row, which every parity symbol contributes to
+        let mut parity: Vec<u32> = sums.iter().map(|&sum| (q - sum) % q).collect();
+        let overall = (sums[0] + parity[1..].iter().sum::<u32>()) % q;
+        parity[0] = (q - overall) % q;
+
+        let mut word = data.to_vec();
+        word.extend(parity.iter().map(|&p| p as u8));
+        word
+    }
+
+    /// Checks a received pattern and fixes a single wrong symbol
+    ///
+    /// # Returns
+    /// * `(Vec<u8>, Correction)` - The pattern (corrected if possible, parity
+    ///   included) and what was done to it
+    pub fn decode(&self, word: &[u8]) -> (Vec<u8>, Correction) {
+        if word.len() != self.encoded_length() {
+            return (word.to_vec(), Correction::Uncorrectable);
+        }
+        let q = self.levels as u32;
+        let syndrome = self.syndrome(word);
+        if syndrome.iter().all(|&s| s == 0) {
+            return (word.to_vec(), Correction::Clean);
+        }
+
+        // A single error of size e at a position gives e times its column
+        let error = syndrome[0];
+        let candidates: Vec<usize> = if error == 0 {
+            Vec::new()
+        } else {
+            (0..word.len())
+                .filter(|&position| {
+                    self.columns[position].iter().zip(&syndrome).all(|(&c, &s)| (c as u32 * error) % q == s)
+                })
+                .collect()
+        };
+
+        match candidates.as_slice() {
+            [position] => {
+                let mut corrected = word.to_vec();
+                corrected[*position] = ((corrected[*position] as u32 % q + q - error) % q) as u8;
+                (corrected, Correction::Corrected { position: *position })
+            }
+            _ => (word.to_vec(), Correction::Uncorrectable),
+        }
+    }
+
+    fn syndrome(&self, word: &[u8]) -> Vec<u32> {
+        let q = self.levels as u32;
+        let mut syndrome = vec![0u32; self.parity_length()];
+        for (column, &symbol) in self.columns.iter().zip(word) {
+            for (s, &c) in syndrome.iter_mut().zip(column) {
+                *s = (*s + c as u32 * (symbol as u32 % q)) % q;
+            }
+        }
+        syndrome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_pattern_decodes_unchanged() {
+        let code = SymbolCode::new(10, 2);
+        let word = code.encode(&[1, 0, 1, 1, 0, 0, 1, 0, 1, 1]);
+        assert_eq!(word.len(), code.encoded_length());
+        assert_eq!(code.decode(&word), (word.clone(), Correction::Clean));
+    }
+
+    #[test]
+    fn corrects_one_wrong_symbol_anywhere() {
+        for levels in [2, 3, 4] {
+            let code = SymbolCode::new(10, levels);
+            let data: Vec<u8> = (0..10).map(|i| (i * 7 % levels as usize) as u8).collect();
+            let word = code.encode(&data);
+
+            for position in 0..word.len() {
+                for error in 1..levels {
+                    let mut received = word.clone();
+                    received[position] = (received[position] + error) % levels;
+                    assert_eq!(
+                        code.decode(&received),
+                        (word.clone(), Correction::Corrected { position }),
+                        "levels {}, position {}, error {}",
+                        levels,
+                        position,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reports_two_wrong_symbols() {
+        for levels in [2, 3] {
+            let code = SymbolCode::new(10, levels);
+            let word = code.encode(&[0, 1, 1, 0, 1, 0, 0, 1, 1, 0]);
+
+            for first in 0..word.len() {
+                for second in first + 1..word.len() {
+                    let mut received = word.clone();
+                    received[first] = (received[first] + 1) % levels;
+                    received[second] = (received[second] + 1) % levels;
+                    assert_eq!(code.decode(&received).1, Correction::Uncorrectable, "positions {} and {}", first, second);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_length_is_uncorrectable() {
+        let code = SymbolCode::new(10, 2);
+        assert_eq!(code.decode(&[0; 5]).1, Correction::Uncorrectable);
+    }
+}
@@ -0,0 +1,6 @@
+// Security module declarations
+pub mod ecc;
+pub mod protocol;
+pub mod rolling_code;
+pub mod secure_pattern_generator;
+pub mod shared_secret;
\ No newline at end of file
@@ -0,0 +1,120 @@
+// LED transmission protocol shared by the LED controller and the camera
+//
+// The LEDs send one frame per time window: a sync preamble shown on every LED,
+// followed by the payload symbols. The camera locks onto the preamble to know
+// where the payload starts, instead of relying on both clocks being in phase.
+// The preamble opens with a run of symbols at the top level on every LED that
+// payloads are never allowed to contain, so it cannot be found inside one.
+use std::time::Duration;
+
+// How long each symbol is shown on the LEDs
+pub const SYMBOL_DURATION: Duration = Duration::from_millis(1000);
+
+// Sync preamble, shown on every LED at the start of each frame
+// (1 = highest brightness level, 0 = off)
+pub const PREAMBLE: [u8; 4] = [1, 1, 1, 0];
+
+// Symbols at the top level on every LED that open the preamble; a payload
+// never has this many in a row
+pub const SYNC_RUN: usize = 3;
+
+/// Number of symbols in a frame carrying `payload_length` payload symbols
+pub fn frame_length(payload_length: usize) -> usize {
+    PREAMBLE.len() + payload_length
+}
+
+/// Level of the preamble at `index` when symbols have `levels` levels
+pub fn preamble_level(index: usize, levels: u8) -> u8 {
+    PREAMBLE[index] * (levels.max(2) - 1)
+}
+
+/// Whether a payload contains the run that opens the preamble, so the decoder
+/// could lock onto it
+///
+/// # Arguments
+/// * `payload` - Payload of a frame, one pattern per LED
+/// * `levels` - Brightness levels per symbol
+pub fn contains_sync_run(payload: &[Vec<u8>], levels: u8) -> bool {
+    let top = levels.max(2) - 1;
+    let length = payload.first().map_or(0, Vec::len);
+    let mut run = 0;
+    for i in 0..length {
+        run = if payload.iter().all(|pattern| pattern[i] == top) { run + 1 } else { 0 };
+        if run >= SYNC_RUN {
+            return true;
+        }
+    }
+    false
+}
+
+/// Symbol level of every LED at `index` within a frame
+///
+/// # Arguments
+/// * `payload` - Payload of the frame, one pattern per LED
+/// * `index` - Symbol position within the frame, preamble included
+/// * `levels` - Brightness levels per symbol
+pub fn frame_symbol(payload: &[Vec<u8>], index: usize, levels: u8) -> Vec<u8> {
+    match index.checked_sub(PREAMBLE.len()) {
+        None => vec![preamble_level(index, levels); payload.len()],
+        Some(i) => payload.iter().map(|pattern| pattern[i]).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::security::rolling_code::RollingCode;
+    use crate::controller::security::secure_pattern_generator::{derive_led_patterns, PatternConfig};
+
+    #[test]
+    fn sync_run_is_found_only_when_every_led_is_at_the_top_level() {
+        assert!(contains_sync_run(&[vec![0, 1, 1, 1, 0], vec![1, 1, 1, 1, 0]], 2));
+        assert!(!contains_sync_run(&[vec![0, 1, 1, 1, 0], vec![1, 1, 0, 1, 0]], 2));
+        assert!(!contains_sync_run(&[vec![2, 2, 1, 2, 2]], 3));
+    }
+
+    #[test]
+    fn frame_starts_with_the_preamble_on_every_led() {
+        let payload = vec![vec![0, 1, 0], vec![1, 1, 0]];
+        assert_eq!(frame_length(payload[0].len()), PREAMBLE.len() + 3);
+        for (index, &level) in PREAMBLE.iter().enumerate() {
+            assert_eq!(frame_symbol(&payload, index, 2), vec![level; 2]);
+        }
+        // Multi-level preambles use the top level
+        assert_eq!(frame_symbol(&payload, 0, 4), vec![3, 3]);
+        assert_eq!(frame_symbol(&payload, 3, 4), vec![0, 0]);
+    }
+
+    #[test]
+    fn frame_continues_with_the_payload_in_order() {
+        let payload = vec![vec![0, 1, 0], vec![1, 1, 0]];
+        let framed: Vec<Vec<u8>> = (PREAMBLE.len()..frame_length(3)).map(|index| frame_symbol(&payload, index, 2)).collect();
+        assert_eq!(framed, vec![vec![0, 1], vec![1, 1], vec![0, 0]]);
+    }
+
+    #[test]
+    fn derived_payloads_never_contain_the_sync_run() {
+        for error_correction in [false, true] {
+            let config = PatternConfig { error_correction, ..PatternConfig::default() };
+            let code = RollingCode::new(b"test secret", &config);
+            for window in 0..500 {
+                assert!(!contains_sync_run(&code.code_for_window(window).unwrap(), config.levels), "window {}", window);
+            }
+        }
+    }
+
+    #[test]
+    fn derivation_fails_rather_than_return_the_sync_run() {
+        // On/off payloads this long always have every LED lit for three symbols somewhere
+        let config = PatternConfig {
+            length: 2000,
+            min_digit: 0,
+            max_digit: 1,
+            repeat_allowed: true,
+            levels: 2,
+            error_correction: false,
+            ..PatternConfig::default()
+        };
+        assert!(derive_led_patterns(&config, b"test secret").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::controller::security::ecc::SymbolCode;
+use crate::controller::security::protocol::{self, SYMBOL_DURATION};
+use crate::controller::security::secure_pattern_generator::{derive_led_patterns, PatternConfig};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Time-based rolling LED code, in the spirit of TOTP
+///
+/// Time is divided into windows of one protocol frame (preamble and payload).
+/// The payload sent during a window is derived from an HMAC of the shared
+/// secret and the window number, so footage recorded during one window does
+/// not verify during another.
+#[derive(Debug, Clone)]
+pub struct RollingCode {
+    secret: Vec<u8>,
+    config: PatternConfig,
+}
+
+impl RollingCode {
+    /// Creates a rolling code generator
+    ///
+    /// # Arguments
+    /// * `secret` - Secret shared by the LED controller and the camera
+    /// * `config` - Pattern parameters; `length` is the number of symbols per
+    ///   window, spread over the configured LEDs and brightness levels
+    pub fn new(secret: &[u8], config: &PatternConfig) -> Self {
+        Self {
+            secret: secret.to_vec(),
+            config: config.clone(),
+        }
+    }
+
+    /// Number of LEDs the code drives
+    pub fn led_count(&self) -> usize {
+        self.config.leds.len()
+    }
+
+    /// Brightness levels per symbol
+    pub fn levels(&self) -> u8 {
+        self.config.levels
+    }
+
+    /// Number of payload symbols per LED and window, parity included
+    pub fn payload_length(&self) -> usize {
+        self.config.payload_length()
+    }
+
+    /// Error-correcting code applied to each LED pattern, if enabled
+    pub fn error_code(&self) -> Option<SymbolCode> {
+        self.config.error_code()
+    }
+
+    /// Duration of one code window
+    pub fn period(&self) -> Duration {
+        SYMBOL_DURATION * protocol::frame_length(self.payload_length()) as u32
+    }
+
+    /// Window number containing `time`
+    pub fn window_at(&self, time: SystemTime) -> u64 {
+        (since_epoch(time).as_millis() / self.period().as_millis()) as u64
+    }
+
+    /// Wall-clock time at which `window` starts
+    pub fn window_start(&self, window: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.period().as_millis() as u64 * window)
+    }
+
+    /// Derives the per-LED payload sent during `window`
+    ///
+    /// # Returns
+    /// * `Result<Vec<Vec<u8>>>` - One pattern of levels per LED, `payload_length()` symbols each
+    pub fn code_for_window(&self, window: u64) -> Result<Vec<Vec<u8>>> {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(&window.to_be_bytes());
+        let window_key = mac.finalize().into_bytes();
+
+        derive_led_patterns(&self.config, &window_key)
+    }
+
+    /// LED states that should be showing at `time`, preamble included
+    pub fn symbols_at(&self, time: SystemTime) -> Result<Vec<u8>> {
+        let millis = since_epoch(time).as_millis();
+        let index = ((millis % self.period().as_millis()) / SYMBOL_DURATION.as_millis()) as usize;
+
+        Ok(protocol::frame_symbol(&self.code_for_window(self.window_at(time))?, index, self.config.levels))
+    }
+
+    /// Time left until the next symbol starts
+    pub fn time_to_next_symbol(&self, time: SystemTime) -> Duration {
+        let symbol_millis = SYMBOL_DURATION.as_millis();
+        let elapsed = since_epoch(time).as_millis() % symbol_millis;
+        Duration::from_millis((symbol_millis - elapsed) as u64)
+    }
+}
+
+fn since_epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> RollingCode {
+        RollingCode::new(b"test secret", &PatternConfig::default())
+    }
+
+    #[test]
+    fn replayed_code_from_an_old_window_does_not_match() {
+        let code = code();
+        let now = code.window_at(SystemTime::now());
+        let replayed = code.code_for_window(now - 10).unwrap();
+
+        // Nothing the camera accepts around the current window matches the replay
+        for window in now - 1..=now + 1 {
+            assert_ne!(code.code_for_window(window).unwrap(), replayed, "window {}", window);
+        }
+    }
+
+    #[test]
+    fn symbols_are_stable_within_a_window_and_change_across_windows() {
+        let code = code();
+        let window = code.window_at(SystemTime::now()) + 1;
+        let start = code.window_start(window);
+        let symbol = |time: SystemTime| code.symbols_at(time).unwrap();
+
+        // Every instant of a symbol shows the same levels
+        for index in 0..protocol::frame_length(code.payload_length()) as u32 {
+            let symbol_start = start + SYMBOL_DURATION * index;
+            assert_eq!(symbol(symbol_start), symbol(symbol_start + SYMBOL_DURATION / 2));
+            assert_eq!(symbol(symbol_start), symbol(symbol_start + SYMBOL_DURATION - Duration::from_millis(1)));
+        }
+        assert_eq!(code.code_for_window(window).unwrap(), code.code_for_window(window).unwrap());
+
+        let frame = |window: u64| -> Vec<Vec<u8>> {
+            let start = code.window_start(window);
+            (0..protocol::frame_length(code.payload_length()) as u32)
+                .map(|index| symbol(start + SYMBOL_DURATION * index))
+                .collect()
+        };
+        assert_ne!(frame(window), frame(window + 1));
+    }
+
+    #[test]
+    fn windows_follow_the_frame_length() {
+        let code = code();
+        let window = 1_000;
+        let start = code.window_start(window);
+        assert_eq!(code.window_at(start), window);
+        assert_eq!(code.window_at(start + code.period() - Duration::from_millis(1)), window);
+        assert_eq!(code.window_at(start + code.period()), window + 1);
+    }
+}
@@ -1,13 +1,74 @@
 use rand::rngs::OsRng;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use anyhow::{Context, Result};
+use std::time::Duration;
+use crate::controller::security::ecc::SymbolCode;
+use crate::controller::security::protocol;
 
-#[derive(Debug, Deserialize)]
+// Default location of the pattern parameters, relative to the repository root
+pub const DEFAULT_CONFIG_PATH: &str = "src/tuning/secure_pattern_parameters.toml";
+
+// Payloads derived before giving up on keeping the sync run out of them
+const MAX_SYNC_ATTEMPTS: usize = 256;
+
+/// One LED the pattern is sent on
+#[derive(Debug, Clone, Deserialize)]
+pub struct LedChannel {
+    pub name: String,        // Label used in logs, e.g. "ACT"
+    pub path: String,        // sysfs brightness file driven by the LED controller
+    #[serde(default = "default_max_brightness")]
+    pub max_brightness: u32, // Value written for the highest level
+}
+
+impl LedChannel {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: format!("/sys/class/leds/{}/brightness", name),
+            max_brightness: default_max_brightness(),
+        }
+    }
+
+    /// Brightness value to write for a symbol level
+    pub fn brightness(&self, level: u8, levels: u8) -> u32 {
+        let top = (levels.max(2) - 1) as u32;
+        (level as u32).min(top) * self.max_brightness / top
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct PatternConfig {
     pub length: usize,
     pub min_digit: u8,
     pub max_digit: u8,
     pub repeat_allowed: bool,
+    #[serde(default = "default_max_clock_skew_ms")]
+    pub max_clock_skew_ms: u64, // Tolerated clock offset between LED controller and camera
+    #[serde(default = "default_levels")]
+    pub levels: u8,             // Brightness levels per symbol, 2 = on/off
+    #[serde(default = "default_leds")]
+    pub leds: Vec<LedChannel>,  // LEDs the pattern is spread over
+    #[serde(default)]
+    pub error_correction: bool, // Append parity symbols so single misread symbols can be fixed
+}
+
+fn default_max_clock_skew_ms() -> u64 {
+    2000
+}
+
+fn default_levels() -> u8 {
+    2
+}
+
+fn default_max_brightness() -> u32 {
+    1
+}
+
+fn default_leds() -> Vec<LedChannel> {
+    vec![LedChannel::new("ACT"), LedChannel::new("PWR")]
 }
 
 impl Default for PatternConfig {
@@ -17,12 +78,161 @@ impl Default for PatternConfig {
             min_digit: 0,
             max_digit: 9,
             repeat_allowed: true,
+            max_clock_skew_ms: default_max_clock_skew_ms(),
+            levels: default_levels(),
+            leds: default_leds(),
+            error_correction: false,
+        }
+    }
+}
+
+impl PatternConfig {
+    /// Loads the pattern parameters from a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the parameters file
+    ///
+    /// # Returns
+    /// * `Result<PatternConfig>` - Validated parameters or error
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .context(format!("Failed to read pattern parameters: {}", path))?;
+        let config: Self = toml::from_str(&text)
+            .context(format!("Failed to parse pattern parameters: {}", path))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Maps a digit of `min_digit..=max_digit` onto a symbol level of `0..levels`
+    ///
+    /// With `levels` equal to the number of digits every digit is sent as-is;
+    /// with fewer levels neighbouring digits share a level.
+    pub fn digit_to_level(&self, digit: u8) -> u8 {
+        let range = (self.max_digit - self.min_digit) as u32;
+        if range == 0 {
+            return 0;
         }
+        let top = (self.levels - 1) as u32;
+        let offset = (digit.clamp(self.min_digit, self.max_digit) - self.min_digit) as u32;
+        ((offset * top * 2 + range) / (range * 2)) as u8
+    }
+
+    /// Error-correcting code applied to each LED pattern, if enabled
+    pub fn error_code(&self) -> Option<SymbolCode> {
+        self.error_correction.then(|| SymbolCode::new(self.length, self.levels))
+    }
+
+    /// Number of symbols each LED sends per frame, parity included
+    pub fn payload_length(&self) -> usize {
+        self.error_code().map_or(self.length, |code| code.encoded_length())
+    }
+
+    /// Tolerated clock offset between LED controller and camera
+    pub fn max_clock_skew(&self) -> Duration {
+        Duration::from_millis(self.max_clock_skew_ms)
+    }
+
+    /// Checks that a pattern can actually be generated from these parameters
+    pub fn validate(&self) -> Result<()> {
+        if self.length == 0 {
+            return Err(anyhow::anyhow!("Pattern length must be at least 1"));
+        }
+        if self.min_digit > self.max_digit {
+            return Err(anyhow::anyhow!(
+                "min_digit ({}) is greater than max_digit ({})",
+                self.min_digit,
+                self.max_digit
+            ));
+        }
+        if self.levels < 2 {
+            return Err(anyhow::anyhow!("At least 2 brightness levels are needed, got {}", self.levels));
+        }
+        if self.leds.is_empty() {
+            return Err(anyhow::anyhow!("At least one LED channel is needed"));
+        }
+        let digits = (self.max_digit - self.min_digit) as usize + 1;
+        if !self.repeat_allowed && self.length > digits {
+            return Err(anyhow::anyhow!(
+                "A pattern of {} digits needs repeats, only {} distinct digits are available",
+                self.length,
+                digits
+            ));
+        }
+        Ok(())
     }
 }
 
 pub fn generate_pattern(config: &PatternConfig) -> Vec<u8> {
-    let mut rng = OsRng;
+    fill_pattern(&mut OsRng, config)
+}
+
+/// Derives a pattern deterministically from a shared secret
+///
+/// Both the LED controller and the camera verifier call this with the same
+/// secret, so they agree on the pattern without it being compiled into either
+/// binary. The `label` separates independent patterns derived from one secret.
+///
+/// # Arguments
+/// * `config` - Pattern parameters
+/// * `secret` - Shared secret
+/// * `label` - Purpose of the pattern, e.g. the LED it drives
+pub fn derive_pattern(config: &PatternConfig, secret: &[u8], label: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(secret);
+    hasher.update([0u8]);
+    hasher.update(label.as_bytes());
+    let seed: [u8; 32] = hasher.finalize().into();
+
+    fill_pattern(&mut ChaCha20Rng::from_seed(seed), config)
+}
+
+/// Derives the symbol pattern for each configured LED from a shared secret
+///
+/// Digits are mapped onto the configured number of brightness levels and,
+/// with `error_correction`, followed by their parity symbols. A payload that
+/// contains the run opening the sync preamble is derived again under a new
+/// label, so the decoder can never lock onto the middle of a payload; the
+/// controller and the camera skip the same payloads.
+///
+/// # Arguments
+/// * `config` - Pattern parameters
+/// * `secret` - Shared secret
+///
+/// # Returns
+/// * `Result<Vec<Vec<u8>>>` - One pattern of levels per LED in `config.leds`,
+///   `config.payload_length()` symbols each, or an error if every attempt
+///   contained the sync run
+pub fn derive_led_patterns(config: &PatternConfig, secret: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let error_code = config.error_code();
+    let derive = |attempt: usize| -> Vec<Vec<u8>> {
+        (0..config.leds.len())
+            .map(|led| {
+                let label = match attempt {
+                    0 => format!("led{}", led),
+                    _ => format!("led{}#{}", led, attempt),
+                };
+                let levels: Vec<u8> = derive_pattern(config, secret, &label)
+                    .into_iter()
+                    .map(|digit| config.digit_to_level(digit))
+                    .collect();
+                match &error_code {
+                    Some(code) => code.encode(&levels),
+                    None => levels,
+                }
+            })
+            .collect()
+    };
+
+    (0..MAX_SYNC_ATTEMPTS)
+        .map(derive)
+        .find(|patterns| !protocol::contains_sync_run(patterns, config.levels))
+        .ok_or_else(|| anyhow::anyhow!(
+            "Every one of {} derived LED payloads contains the sync run: use more levels, fewer LEDs or a shorter pattern",
+            MAX_SYNC_ATTEMPTS
+        ))
+}
+
+fn fill_pattern<R: Rng>(rng: &mut R, config: &PatternConfig) -> Vec<u8> {
     let mut pattern = Vec::with_capacity(config.length);
 
     while pattern.len() < config.length {
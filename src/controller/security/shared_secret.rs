@@ -0,0 +1,49 @@
+use rand::rngs::OsRng;
+use rand::RngCore;
+use anyhow::{Context, Result};
+
+// Default location of the shared secret (hex encoded), never committed
+pub const DEFAULT_SECRET_PATH: &str = "secret.key";
+
+// Environment variable that overrides the secret file
+pub const SECRET_ENV_VAR: &str = "SECURITY_CAMERA_SECRET";
+
+// Length of generated secrets in bytes
+pub const SECRET_LENGTH: usize = 32;
+
+/// Generates a new random secret
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Loads the shared secret
+///
+/// The `SECURITY_CAMERA_SECRET` environment variable takes precedence over the
+/// file. Both hold the secret hex encoded.
+///
+/// # Arguments
+/// * `path` - Path to the secret file
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - Secret bytes or error
+pub fn load_secret(path: &str) -> Result<Vec<u8>> {
+    let text = match std::env::var(SECRET_ENV_VAR) {
+        Ok(value) => value,
+        Err(_) => std::fs::read_to_string(path)
+            .context(format!("Failed to read shared secret: {} (or set {})", path, SECRET_ENV_VAR))?,
+    };
+
+    let secret = hex::decode(text.trim()).context("Shared secret is not valid hex")?;
+    if secret.is_empty() {
+        return Err(anyhow::anyhow!("Shared secret is empty"));
+    }
+    Ok(secret)
+}
+
+/// Writes a secret to `path`, hex encoded
+pub fn save_secret(path: &str, secret: &[u8]) -> Result<()> {
+    std::fs::write(path, hex::encode(secret))
+        .context(format!("Failed to write shared secret: {}", path))
+}
@@ -0,0 +1,47 @@
+// Library entry point: exposes the camera pipeline so other binaries can embed it
+pub mod controller;
+
+#[cfg(feature = "camera")]
+pub use controller::camera::background::{BackgroundConfig, BackgroundModel, BackgroundModelKind};
+#[cfg(feature = "camera")]
+pub use controller::camera::capture::{CalibrationProgress, MotionDetector};
+#[cfg(feature = "camera")]
+pub use controller::camera::catalogue::{Catalogue, CatalogueConfig, EventQuery, EventRecord, FileKind, VerdictRecord};
+#[cfg(feature = "camera")]
+pub use controller::camera::classifier::{ClassifierConfig, LedClassifier, LedColor};
+#[cfg(feature = "camera")]
+pub use controller::camera::config::{CalibrationConfig, CameraConfig};
+#[cfg(feature = "camera")]
+pub use controller::camera::event::{BufferedFrame, EventConfig, FrameBuffer};
+#[cfg(feature = "camera")]
+pub use controller::camera::localizer::LedLocalizer;
+#[cfg(feature = "camera")]
+pub use controller::camera::logging;
+#[cfg(feature = "camera")]
+pub use controller::camera::manifest::{Manifest, ManifestAction, ManifestConfig, ManifestEntry, ManifestHead, ManifestProblem, VerifyReport};
+#[cfg(feature = "camera")]
+pub use controller::camera::policy::{StateTransition, VerificationPolicy, VerificationState, VerificationStateMachine};
+#[cfg(feature = "camera")]
+pub use controller::camera::profile::CalibrationProfile;
+#[cfg(feature = "camera")]
+pub use controller::camera::recording::{ContinuousRecorder, EventMarker, RecordingConfig};
+#[cfg(feature = "camera")]
+pub use controller::camera::retention::{Retention, RetentionConfig, RetentionJanitor, SweepReport};
+#[cfg(feature = "camera")]
+pub use controller::camera::scene::{SceneHealthConfig, SceneHealthMonitor};
+#[cfg(feature = "camera")]
+pub use controller::camera::simulator::{Fault, LedSimulator, SceneConfig, SimulatedLed, TimingModel};
+#[cfg(feature = "camera")]
+pub use controller::camera::source::{open_camera, open_source, FrameSource};
+#[cfg(feature = "camera")]
+pub use controller::camera::storage::{EventRecorder, VideoStorage};
+#[cfg(feature = "camera")]
+pub use controller::camera::tamper::{TamperConfig, TamperDetector, TamperEvent};
+#[cfg(feature = "camera")]
+pub use controller::camera::verdict::{CorrectionReport, ReadQuality, Verdict, VerdictStatus};
+#[cfg(feature = "camera")]
+pub use controller::camera::verifier::{Calibration, ExpectedCode, ExpectedPatterns, LedRois, LedVerifier, Thresholds};
+#[cfg(feature = "camera")]
+pub use controller::camera::zones::{MotionConfig, MotionRegion, MotionResult, MotionZones, ZoneConfig, ZoneKind};
+pub use controller::security::secure_pattern_generator::{derive_pattern, generate_pattern, PatternConfig};
+pub use controller::security::ecc::{Correction, SymbolCode};
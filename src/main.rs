@@ -1,285 +1,350 @@
 use anyhow::Result;
 use opencv::{
-    core::{self, Mat, Point, Rect, Scalar},
+    core::{Mat, Point, Scalar},
     highgui,
     imgproc,
     prelude::*,
-    videoio::{self, VideoCapture},
 };
-use std::time::{Duration, Instant};
-
-// Expected LED patterns from led_controller.rs
-const LED1_PATTERN: [u8; 4] = [0, 0, 1, 0]; // ACT LED pattern
-const LED2_PATTERN: [u8; 4] = [0, 1, 1, 0]; // PWR LED pattern
-const PATTERN_LENGTH: usize = 4;
-const SAMPLE_INTERVAL: Duration = Duration::from_millis(1000); // 1-second sampling to match LED timing
-const CALIBRATION_SAMPLES: usize = 10; // Number of samples for calibration
-const MIN_BRIGHTNESS_DIFF: f64 = 2.0; // Minimum difference between max and min brightness
-const VERIFICATION_HOLD_DURATION: Duration = Duration::from_millis(3500); // Hold verified status for 3 seconds
-
-fn calibrate_thresholds(cap: &mut VideoCapture, roi1: Rect, roi2: Rect, window_name: &str) -> Result<(f64, f64)> {
-    let mut led1_brightnesses = Vec::with_capacity(CALIBRATION_SAMPLES);
-    let mut led2_brightnesses = Vec::with_capacity(CALIBRATION_SAMPLES);
-
-    println!("Starting calibration phase...");
-
-    loop {
-        led1_brightnesses.clear();
-        led2_brightnesses.clear();
-
-        // Collect 10 samples
-        for i in 0..CALIBRATION_SAMPLES {
-            let mut frame = Mat::default();
-            cap.read(&mut frame)?;
-            if frame.empty() {
-                return Err(anyhow::anyhow!("Failed to capture frame during calibration"));
-            }
+use rust_security_camera::controller::camera::catalogue::parse_time;
+use rust_security_camera::controller::camera::config::DEFAULT_CAMERA_CONFIG_PATH;
+use rust_security_camera::controller::camera::verifier::CALIBRATION_SAMPLES;
+use rust_security_camera::controller::security::rolling_code::RollingCode;
+use rust_security_camera::controller::security::secure_pattern_generator::DEFAULT_CONFIG_PATH;
+use rust_security_camera::controller::security::shared_secret::{self, DEFAULT_SECRET_PATH};
+use rust_security_camera::{
+    logging, open_source, CalibrationProfile, CameraConfig, Catalogue, ContinuousRecorder, EventQuery, FileKind, Manifest, ManifestHead, Retention, RetentionJanitor, ExpectedCode, FrameSource, LedRois, LedSimulator, LedVerifier,
+    MotionDetector, MotionResult, PatternConfig, SceneConfig, SceneHealthMonitor, TamperDetector, Thresholds, TimingModel, VerdictStatus, VerificationState,
+};
 
-            let led1_region = Mat::roi(&frame, roi1)?;
-            let led2_region = Mat::roi(&frame, roi2)?;
-
-            let led1_brightness = core::mean(&led1_region, &Mat::default())?.0[0];
-            let led2_brightness = core::mean(&led2_region, &Mat::default())?.0[0];
-
-            led1_brightnesses.push(led1_brightness);
-            led2_brightnesses.push(led2_brightness);
-
-            println!(
-                "Calibration sample {}: LED1 Brightness = {:.2}, LED2 Brightness = {:.2}",
-                i + 1,
-                led1_brightness,
-                led2_brightness
-            );
-
-            // Draw ROI1 rectangle (red, thickness 2) for LED1
-            imgproc::rectangle(
-                &mut frame,
-                roi1,
-                Scalar::new(0.0, 0.0, 255.0, 0.0), // Red color in BGR
-                2,
-                imgproc::LINE_8,
-                0,
-            )?;
-
-            // Draw ROI2 rectangle (blue, thickness 2) for LED2
-            imgproc::rectangle(
-                &mut frame,
-                roi2,
-                Scalar::new(255.0, 0.0, 0.0, 0.0), // Blue color in BGR
-                2,
-                imgproc::LINE_8,
-                0,
-            )?;
-
-            // Display calibration status
-            let text = format!("Calibration in progress: Sample {}/{}", i + 1, CALIBRATION_SAMPLES);
-            imgproc::put_text(
-                &mut frame,
-                &text,
-                Point::new(10, 30), // Position near top-left
-                imgproc::FONT_HERSHEY_SIMPLEX,
-                0.7, // Font scale
-                Scalar::new(0.0, 255.0, 0.0, 0.0), // Green text
-                2,
-                imgproc::LINE_8,
-                false,
-            )?;
-
-            // Display the frame
-            highgui::imshow(window_name, &frame)?;
-
-            // Check for 'Esc' key to exit calibration
-            if highgui::wait_key(1)? == 27 {
-                return Err(anyhow::anyhow!("Calibration interrupted by user (Esc key)"));
-            }
+const DEFAULT_SOURCE: &str = "camera:1"; // Webcam index 1 unless a source is given on the command line
+const MOTION_THRESHOLD: f64 = 25.0; // Pixel difference threshold for motion
+const MIN_CONTOUR_AREA: f64 = 500.0; // Minimum contour area (pixels) counted as motion
+const STORAGE_DIR: &str = "footage"; // Directory for captured frames and clips
+
+// Colours the LED regions are drawn in, cycled per LED (BGR)
+const ROI_COLORS: [(f64, f64, f64); 4] = [(0.0, 0.0, 255.0), (255.0, 0.0, 0.0), (0.0, 255.0, 255.0), (255.0, 0.0, 255.0)];
+
+/// Draws the LED regions onto a frame: LED1 in red, LED2 in blue, then yellow and magenta
+fn draw_rois(frame: &mut Mat, rois: &LedRois) -> Result<()> {
+    for (i, &roi) in rois.rects.iter().enumerate() {
+        let (b, g, r) = ROI_COLORS[i % ROI_COLORS.len()];
+        imgproc::rectangle(frame, roi, Scalar::new(b, g, r, 0.0), 2, imgproc::LINE_8, 0)?;
+    }
+    Ok(())
+}
+
+/// Draws the bounding boxes of the motion regions in green
+fn draw_motion(frame: &mut Mat, motion: &MotionResult) -> Result<()> {
+    for region in &motion.regions {
+        imgproc::rectangle(frame, region.bbox, Scalar::new(0.0, 255.0, 0.0, 0.0), 1, imgproc::LINE_8, 0)?;
+    }
+    Ok(())
+}
+
+/// Draws a status line near the top-left corner of a frame
+fn draw_status(frame: &mut Mat, text: &str) -> Result<()> {
+    imgproc::put_text(
+        frame,
+        text,
+        Point::new(10, 30), // Position near top-left
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.7, // Font scale
+        Scalar::new(0.0, 255.0, 0.0, 0.0), // Green text
+        2,
+        imgproc::LINE_8,
+        false,
+    )?;
+    Ok(())
+}
+
+/// Calibrates the LED thresholds, previewing every sample
+///
+/// # Returns
+/// * `Result<Thresholds>` - Calibrated brightness ranges, also installed in the detector
+fn calibrate_thresholds(detector: &mut MotionDetector, rois: &LedRois, expected: &ExpectedCode, window_name: &str) -> Result<Thresholds> {
+    detector.calibrate(rois.clone(), expected.clone(), |frame, progress| {
+        let readings: Vec<String> = progress.brightnesses.iter().enumerate()
+            .map(|(i, brightness)| format!("LED{} Brightness = {:.2}", i + 1, brightness))
+            .collect();
+        println!("Calibration sample {}: {}", progress.sample, readings.join(", "));
+
+        let mut preview = frame.clone();
+        draw_rois(&mut preview, rois)?;
+        draw_status(
+            &mut preview,
+            &format!(
+                "Calibration in progress: Sample {}/{} (attempt {}/{})",
+                progress.sample, CALIBRATION_SAMPLES, progress.attempt, progress.max_attempts
+            ),
+        )?;
+
+        // Display the frame
+        highgui::imshow(window_name, &preview)?;
 
-            std::thread::sleep(SAMPLE_INTERVAL);
+        // Check for 'Esc' key to exit calibration
+        if highgui::wait_key(1)? == 27 {
+            return Err(anyhow::anyhow!("Calibration interrupted by user (Esc key)"));
         }
+        Ok(())
+    })?;
 
-        // Calculate max, min, and threshold for each LED
-        let led1_max = led1_brightnesses.iter().fold(f64::MIN, |a, &b| a.max(b));
-        let led1_min = led1_brightnesses.iter().fold(f64::MAX, |a, &b| a.min(b));
-        let led2_max = led2_brightnesses.iter().fold(f64::MIN, |a, &b| a.max(b));
-        let led2_min = led2_brightnesses.iter().fold(f64::MAX, |a, &b| a.min(b));
+    let verifier = detector.verifier().ok_or_else(|| anyhow::anyhow!("Calibration did not install a verifier"))?;
+    Ok(verifier.thresholds().clone())
+}
 
-        let led1_diff = led1_max - led1_min;
-        let led2_diff = led2_max - led2_min;
+/// Reloads the saved calibration profile if it still matches what the camera sees
+fn load_profile(detector: &mut MotionDetector, expected: &ExpectedCode, camera_config: &CameraConfig) -> Result<Option<CalibrationProfile>> {
+    let Some(path) = camera_config.calibration.profile_path() else {
+        return Ok(None);
+    };
+    let Some(profile) = CalibrationProfile::load(path)? else {
+        return Ok(None);
+    };
+
+    println!("Checking calibration profile from {} ({})", path, profile.created_at);
+    if detector.validate_profile(&profile, expected)? {
+        println!("Calibration profile is valid, skipping calibration");
+        Ok(Some(profile))
+    } else {
+        println!("Calibration profile is out of date, recalibrating");
+        Ok(None)
+    }
+}
 
+/// Finds the LED regions: located from the blink pattern if possible, otherwise
+/// taken from the camera config, otherwise the top-right corner of the frame
+fn find_led_rois(
+    detector: &mut MotionDetector,
+    expected: &ExpectedCode,
+    camera_config: &CameraConfig,
+    frame: &Mat,
+) -> Result<LedRois> {
+    if camera_config.localization.enabled {
+        if let Some(rois) = detector.locate_leds(expected, &camera_config.localization)? {
+            return Ok(rois);
+        }
+    }
+
+    if let Some(rois) = camera_config.configured_rois(expected.led_count()) {
+        println!("Using LED ROIs from {}", DEFAULT_CAMERA_CONFIG_PATH);
+        return Ok(rois);
+    }
+
+    println!("Using default LED ROIs in the top-right corner");
+    Ok(LedRois::top_right(frame.cols(), frame.rows(), expected.led_count()))
+}
+
+/// Lists the catalogued footage matching the command line filters
+///
+/// # Arguments
+/// * `camera_config` - Camera parameters, for the catalogue location
+/// * `args` - Filters: `--from <time>`, `--to <time>`, `--kind frame|clip|segment`,
+///   `--type <event type>`, `--verdict <status>` and `--camera <camera id>`
+fn list_events(camera_config: &CameraConfig, args: &[String]) -> Result<()> {
+    let mut query = EventQuery::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for {}", flag))?;
+        match flag.as_str() {
+            "--from" => query.from = Some(parse_time(value)?),
+            "--to" => query.to = Some(parse_time(value)?),
+            "--kind" => {
+                query.kind = Some(match value.as_str() {
+                    "frame" => FileKind::Frame,
+                    "clip" => FileKind::Clip,
+                    "segment" => FileKind::Segment,
+                    _ => return Err(anyhow::anyhow!("Unknown kind '{}', expected frame, clip or segment", value)),
+                })
+            }
+            "--type" => query.event_type = Some(value.clone()),
+            "--verdict" => query.verdict = Some(value.clone()),
+            "--camera" => query.camera_id = Some(value.clone()),
+            _ => return Err(anyhow::anyhow!("Unknown filter: {}", flag)),
+        }
+    }
+
+    let records = Catalogue::new(camera_config.catalogue.clone()).query(&query)?;
+    for record in &records {
+        let verdict = record.verdict.as_ref()
+            .map_or("-".to_string(), |v| format!("{} ({:.0}%)", v.status, v.confidence * 100.0));
         println!(
-            "LED1: Max = {:.2}, Min = {:.2}, Diff = {:.2}",
-            led1_max, led1_min, led1_diff
-        );
-        println!(
-            "LED2: Max = {:.2}, Min = {:.2}, Diff = {:.2}",
-            led2_max, led2_min, led2_diff
+            "{}  {:>6.1}s  {:<12} {:<8} {}  {}",
+            record.start.format("%Y-%m-%d %H:%M:%S"),
+            record.duration_s,
+            record.event_type,
+            record.kind.label(),
+            verdict,
+            record.file
         );
+    }
+    println!("{} records", records.len());
+    Ok(())
+}
 
-        // Check if differences meet the minimum requirement
-        if led1_diff >= MIN_BRIGHTNESS_DIFF && led2_diff >= MIN_BRIGHTNESS_DIFF {
-            let led1_threshold = (led1_max + led1_min) / 2.0;
-            let led2_threshold = (led2_max + led2_min) / 2.0;
-            println!(
-                "Calibration successful: LED1 Threshold = {:.2}, LED2 Threshold = {:.2}",
-                led1_threshold, led2_threshold
-            );
-            return Ok((led1_threshold, led2_threshold));
-        } else {
-            println!(
-                "Calibration failed: LED1 Diff = {:.2}, LED2 Diff = {:.2}. Retrying...",
-                led1_diff, led2_diff
-            );
+/// Checks the footage manifest's hash chain and the stored footage against it
+///
+/// # Arguments
+/// * `camera_config` - Camera parameters, for the manifest and recording locations
+/// * `args` - `--head <sequence>:<hash>`, a head printed by an earlier check
+fn verify_footage(camera_config: &CameraConfig, args: &[String]) -> Result<()> {
+    let mut exported: Option<ManifestHead> = None;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for {}", flag))?;
+        match flag.as_str() {
+            "--head" => exported = Some(value.parse()?),
+            _ => return Err(anyhow::anyhow!("Unknown option: {}", flag)),
         }
     }
+
+    let secret = shared_secret::load_secret(DEFAULT_SECRET_PATH)?;
+    let directories = vec![STORAGE_DIR.into(), camera_config.recording.directory.clone().into()];
+    let report = Manifest::new(camera_config.manifest.clone(), &secret).verify(&directories, exported.as_ref())?;
+    for problem in &report.problems {
+        println!("{}", problem);
+    }
+    println!("{} entries, {} stored files checked, {} problems", report.entries, report.files, report.problems.len());
+    if let Some(head) = report.head.as_ref() {
+        // Kept off the camera, this catches entries cut off the end even if the head file goes too
+        println!("Head: {} (check against it later with --head)", head);
+    }
+    if !report.is_intact() {
+        return Err(anyhow::anyhow!("Footage does not match {}", camera_config.manifest.path));
+    }
+    println!("Footage intact");
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    // Initialize the webcam capture (index 1 as specified)
-    let mut cap = VideoCapture::new(1, videoio::CAP_ANY)?;
-    if !cap.is_opened()? {
-        return Err(anyhow::anyhow!("Failed to open webcam"));
+    // `events [filters]` lists the catalogued footage and `verify` checks it, instead of monitoring
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "events") {
+        return list_events(&CameraConfig::load(DEFAULT_CAMERA_CONFIG_PATH)?, &args[1..]);
+    }
+    if args.first().is_some_and(|arg| arg == "verify") {
+        return verify_footage(&CameraConfig::load(DEFAULT_CAMERA_CONFIG_PATH)?, &args[1..]);
     }
 
+    // Expected LED code, rolling with time and derived from the secret shared with the LED controller
+    let config = PatternConfig::load(DEFAULT_CONFIG_PATH)?;
+    let camera_config = CameraConfig::load(DEFAULT_CAMERA_CONFIG_PATH)?;
+    let secret = shared_secret::load_secret(DEFAULT_SECRET_PATH)?;
+    let expected = ExpectedCode::Rolling {
+        code: RollingCode::new(&secret, &config),
+        max_skew: config.max_clock_skew(),
+    };
+
+    // Frame source: camera:<index>, file:<path>, images:<dir>, synthetic or simulator
+    let source_spec = args.first().cloned().unwrap_or_else(|| DEFAULT_SOURCE.to_string());
+    let source: Box<dyn FrameSource> = if source_spec == "simulator" {
+        Box::new(LedSimulator::from_code(SceneConfig::default(), TimingModel::default(), &expected)?)
+    } else {
+        open_source(&source_spec)?
+    };
+
+    // Initialize motion detection and storage
+    let mut detector = MotionDetector::from_source(source, MOTION_THRESHOLD, MIN_CONTOUR_AREA, STORAGE_DIR)?;
+    detector.set_calibration_config(camera_config.calibration.clone());
+    detector.set_classifier_config(camera_config.classifier.clone());
+    detector.set_background_config(camera_config.background.clone())?;
+    detector.set_motion_config(camera_config.motion.clone());
+    detector.set_event_config(camera_config.event.clone());
+    if camera_config.tamper.enabled {
+        detector.set_tamper_detector(TamperDetector::new(camera_config.tamper.clone()));
+    }
+    if camera_config.scene.enabled {
+        detector.set_scene_monitor(SceneHealthMonitor::new(camera_config.scene.clone())?);
+    }
+    let catalogue = camera_config.catalogue.enabled.then(|| Catalogue::new(camera_config.catalogue.clone()));
+    if let Some(catalogue) = catalogue.as_ref() {
+        detector.set_catalogue(catalogue.clone());
+    }
+    let manifest = camera_config.manifest.enabled.then(|| Manifest::new(camera_config.manifest.clone(), &secret));
+    if let Some(manifest) = manifest.as_ref() {
+        detector.set_manifest(manifest.clone())?;
+    }
+    if camera_config.recording.enabled {
+        let mut recorder = ContinuousRecorder::new(camera_config.recording.clone())?;
+        if let Some(catalogue) = catalogue.as_ref() {
+            recorder = recorder.with_catalogue(catalogue.clone());
+        }
+        if let Some(manifest) = manifest.as_ref() {
+            recorder = recorder.with_manifest(manifest.clone())?;
+        }
+        detector.set_continuous_recorder(recorder);
+    }
+
+    // Delete old footage in the background, oldest first, keeping anything flagged as evidence
+    let mut janitor = camera_config.retention.enabled.then(|| {
+        let directories = vec![STORAGE_DIR.into(), camera_config.recording.directory.clone().into()];
+        let mut retention = Retention::new(camera_config.retention.clone(), directories);
+        if let Some(manifest) = manifest.as_ref() {
+            retention = retention.with_manifest(manifest.clone());
+        }
+        RetentionJanitor::spawn(retention)
+    });
+
     let window_name = "Webcam Display";
     highgui::named_window(window_name, highgui::WINDOW_AUTOSIZE)?;
 
     // Get frame dimensions for ROI setup
-    let mut frame = Mat::default();
-    cap.read(&mut frame)?;
-    if frame.empty() {
-        return Err(anyhow::anyhow!("Failed to capture initial frame"));
+    let frame = detector.capture_frame()?
+        .ok_or_else(|| anyhow::anyhow!("Failed to capture initial frame"))?;
+    let (rois, thresholds, calibrated) = match load_profile(&mut detector, &expected, &camera_config)? {
+        Some(profile) => (profile.led_rois(), profile.thresholds(), false),
+        None => {
+            let rois = find_led_rois(&mut detector, &expected, &camera_config, &frame)?;
+
+            // Perform calibration to determine thresholds, saving the profile and the reference scene
+            let thresholds = calibrate_thresholds(&mut detector, &rois, &expected, window_name)?;
+            (rois, thresholds, true)
+        }
+    };
+
+    // Reference scene for the covered, blurred and moved camera checks, kept with the calibration
+    if !calibrated {
+        let reference = detector.capture_frame()?
+            .ok_or_else(|| anyhow::anyhow!("Failed to capture the reference scene"))?;
+        if !detector.load_scene_reference(reference.cols(), reference.rows())? {
+            detector.set_scene_reference(&reference)?;
+        }
     }
-    let width = frame.cols();
-    let height = frame.rows();
-
-    // Define the main ROI (top-right corner, 10% of frame size)
-    let rect_width = (width as f32 * 0.1) as f32; // 10% of frame width
-    let rect_height = (height as f32 * 0.1) as f32; // 10% of frame height
-    let top_left_x = width as f32 - rect_width - 10.0; // 10-pixel margin from right
-    let top_left_y = 10.0; // 10-pixel margin from top
-
-    // Split the main ROI into two rectangles: ROI1 (left) for LED1, ROI2 (right) for LED2
-    let roi1 = Rect::new(top_left_x as i32, top_left_y as i32, (rect_width / 2.0) as i32, rect_height as i32); // Left half for LED1
-    let roi2 = Rect::new(
-        (top_left_x + rect_width / 2.0) as i32,
-        top_left_y as i32,
-        (rect_width / 2.0) as i32,
-        rect_height as i32,
-    ); // Right half for LED2
-
-    // Perform calibration to determine thresholds
-    let (brightness_threshold_led1, brightness_threshold_led2) = calibrate_thresholds(&mut cap, roi1, roi2, window_name)?;
-
-    // Buffers to store detected LED states
-    let mut led1_states: Vec<u8> = Vec::with_capacity(PATTERN_LENGTH);
-    let mut led2_states: Vec<u8> = Vec::with_capacity(PATTERN_LENGTH);
-    let mut last_sample_time = Instant::now();
-    let mut is_verified = false;
-    let mut last_verified_time: Option<Instant> = None;
-
-    loop {
-        let mut frame = Mat::default();
-        cap.read(&mut frame)?;
-
-        if frame.empty() {
-            break;
+    let mut verifier = LedVerifier::new(rois.clone(), thresholds, expected)
+        .with_classifier(camera_config.classifier.clone())
+        .with_policy(camera_config.verification.clone());
+    verifier.on_transition(|transition| {
+        let event = format!("Verification state {} -> {} after {} failed windows", transition.from, transition.to, transition.failed_windows);
+        println!("{} ({})", event, transition.verdict);
+        if let Err(e) = logging::log_verified_event(&event, &transition.verdict) {
+            println!("Failed to log verification state change: {}", e);
         }
-
-        // Extract ROI1 and ROI2 from the frame
-        let led1_region = Mat::roi(&frame, roi1)?;
-        let led2_region = Mat::roi(&frame, roi2)?;
-
-        // Sample LED states at 1-second intervals
-        if last_sample_time.elapsed() >= SAMPLE_INTERVAL {
-            // Compute average brightness for each LED region
-            let led1_brightness = core::mean(&led1_region, &Mat::default())?.0[0]; // Use first channel (BGR)
-            let led2_brightness = core::mean(&led2_region, &Mat::default())?.0[0];
-
-            // Determine LED states based on brightness threshold
-            let led1_state = if led1_brightness > brightness_threshold_led1 { 1 } else { 0 };
-            let led2_state = if led2_brightness > brightness_threshold_led2 { 1 } else { 0 };
-
-            // Print brightness and state for each LED
-            println!(
-                "LED1 (ROI1): Brightness = {:.2}, State = {} ({})",
-                led1_brightness,
-                led1_state,
-                if led1_state == 1 { "ON" } else { "OFF" }
-            );
-            println!(
-                "LED2 (ROI2): Brightness = {:.2}, State = {} ({})",
-                led2_brightness,
-                led2_state,
-                if led2_state == 1 { "ON" } else { "OFF" }
-            );
-
-            // Store states
-            led1_states.push(led1_state);
-            led2_states.push(led2_state);
-
-            // Keep only the last PATTERN_LENGTH states
-            if led1_states.len() > PATTERN_LENGTH {
-                led1_states.remove(0);
-            }
-            if led2_states.len() > PATTERN_LENGTH {
-                led2_states.remove(0);
-            }
-
-            // Check if patterns match, but only update verification status if not in hold period
-            if last_verified_time.map_or(true, |t| t.elapsed() >= VERIFICATION_HOLD_DURATION) {
-                if led1_states.len() == PATTERN_LENGTH && led2_states.len() == PATTERN_LENGTH {
-                    is_verified = led1_states == LED1_PATTERN && led2_states == LED2_PATTERN;
-                    if is_verified {
-                        last_verified_time = Some(Instant::now());
-                    } else {
-                        last_verified_time = None;
-                    }
-                } else {
-                    is_verified = false;
-                    last_verified_time = None;
-                }
-            }
-
-            // Reset sampling time
-            last_sample_time = Instant::now();
+    });
+    detector.set_verifier(verifier);
+
+    // Verify the LED pattern and record motion events with their verdict
+    while let Some(mut frame) = detector.capture_frame()? {
+        let report = detector.process_frame(&frame)?;
+        for event in &report.tamper {
+            println!("Tamper detected: {}", event);
         }
 
-        // Draw ROI1 rectangle (red, thickness 2) for LED1
-        imgproc::rectangle(
-            &mut frame,
-            roi1,
-            Scalar::new(0.0, 0.0, 255.0, 0.0), // Red color in BGR
-            2,
-            imgproc::LINE_8,
-            0,
-        )?;
+        draw_rois(&mut frame, &rois)?;
+        draw_motion(&mut frame, &report.motion)?;
 
-        // Draw ROI2 rectangle (blue, thickness 2) for LED2
-        imgproc::rectangle(
-            &mut frame,
-            roi2,
-            Scalar::new(255.0, 0.0, 0.0, 0.0), // Blue color in BGR
-            2,
-            imgproc::LINE_8,
-            0,
-        )?;
-
-        // Display verification status
-        let text = if is_verified {
-            "Footage verified"
-        } else {
-            "Footage not verified"
+        // Display verification status and confidence
+        let status = match report.verdict.status {
+            VerdictStatus::Verified => "Footage verified",
+            VerdictStatus::Unverified => "Footage not verified",
+            VerdictStatus::Indeterminate => "Footage verification indeterminate",
         };
-        imgproc::put_text(
-            &mut frame,
-            text,
-            Point::new(10, 30), // Position near top-left
-            imgproc::FONT_HERSHEY_SIMPLEX,
-            0.7, // Font scale
-            Scalar::new(0.0, 255.0, 0.0, 0.0), // Green text
-            2,
-            imgproc::LINE_8,
-            false,
-        )?;
+        let mut text = format!("{} ({:.0}%)", status, report.verdict.confidence * 100.0);
+        if report.state != VerificationState::Verified {
+            text.push_str(&format!(" - {}", report.state));
+        }
+        if report.in_event {
+            text.push_str(" - recording motion");
+        }
+        draw_status(&mut frame, &text)?;
 
         // Display the frame
         highgui::imshow(window_name, &frame)?;
@@ -290,6 +355,10 @@ fn main() -> Result<()> {
         }
     }
 
+    detector.finish()?;
+    if let Some(janitor) = janitor.as_mut() {
+        janitor.stop();
+    }
     highgui::destroy_all_windows()?;
     Ok(())
-}
\ No newline at end of file
+}